@@ -0,0 +1,111 @@
+// Captures live fbrapi responses as the fixtures the model tests parse,
+// trimmed to a few fixtures and players so they stay readable.
+//
+//   cargo run --example capture_fbrapi -- [league_id] [season_id] [match_id]
+//
+// Defaults to the first played Premier League 2023-2024 match. Uses, and if
+// needed generates, the API key the scrapers use. The fixtures are written
+// over; the values the tests assert on then need updating to match.
+use cnxns::config::{Config, CONFIG_PATH};
+use cnxns::db::api::api_key::send_with_key;
+use cnxns::db::archive::{FIXTURES_ENDPOINT, MATCH_STATS_ENDPOINT};
+use dotenv::dotenv;
+use reqwest::blocking::Client as APIClient;
+use serde_json::Value;
+use std::env;
+use std::fs;
+
+const FIXTURES_DIR: &str = "tests/fixtures/fbrapi";
+const FIXTURES_KEPT: usize = 3;
+const PLAYERS_KEPT: usize = 2;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let league_id = args.first().map_or("9", String::as_str);
+    let season_id = args.get(1).map_or("2023-2024", String::as_str);
+    let config = Config::load(CONFIG_PATH)?;
+    let api_client = APIClient::new();
+
+    let mut fixtures = request(
+        &api_client,
+        &config,
+        FIXTURES_ENDPOINT,
+        &[("league_id", league_id), ("season_id", season_id)],
+    )?;
+    let match_id = match args.get(2) {
+        Some(match_id) => match_id.clone(),
+        None => first_played(&fixtures).ok_or("No played match in the fixture list")?,
+    };
+    trim_fixtures(&mut fixtures, &match_id)?;
+
+    let mut match_stats = request(
+        &api_client,
+        &config,
+        MATCH_STATS_ENDPOINT,
+        &[("match_id", &match_id)],
+    )?;
+    trim_players(&mut match_stats)?;
+
+    write(FIXTURES_ENDPOINT, &fixtures)?;
+    write(MATCH_STATS_ENDPOINT, &match_stats)?;
+    Ok(())
+}
+
+fn request(
+    api_client: &APIClient,
+    config: &Config,
+    endpoint: &str,
+    params: &[(&str, &str)],
+) -> Result<Value, Box<dyn std::error::Error>> {
+    println!("Requesting {} {:?}", endpoint, params);
+    Ok(send_with_key(config, |api_key| {
+        api_client
+            .get(format!("https://fbrapi.com/{}", endpoint))
+            .header("X-API-Key", api_key)
+            .query(params)
+    })?)
+}
+
+fn first_played(fixtures: &Value) -> Option<String> {
+    fixtures["data"]
+        .as_array()?
+        .iter()
+        .find(|fixture| !fixture["home_team_score"].is_null())
+        .and_then(|fixture| fixture["match_id"].as_str())
+        .map(str::to_string)
+}
+
+// The captured match first, then the next few fixtures as listed
+fn trim_fixtures(fixtures: &mut Value, match_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let data = fixtures["data"]
+        .as_array_mut()
+        .ok_or("Missing 'data' array in fixtures")?;
+    let position = data
+        .iter()
+        .position(|fixture| fixture["match_id"] == match_id)
+        .ok_or_else(|| format!("Match {} not in the fixture list", match_id))?;
+    let captured = data.remove(position);
+    data.truncate(FIXTURES_KEPT - 1);
+    data.insert(0, captured);
+    Ok(())
+}
+
+fn trim_players(match_stats: &mut Value) -> Result<(), Box<dyn std::error::Error>> {
+    let teams = match_stats["data"]
+        .as_array_mut()
+        .ok_or("Missing 'data' array in match stats")?;
+    for team in teams {
+        if let Some(players) = team["players"].as_array_mut() {
+            players.truncate(PLAYERS_KEPT);
+        }
+    }
+    Ok(())
+}
+
+fn write(endpoint: &str, response: &Value) -> Result<(), Box<dyn std::error::Error>> {
+    let path = format!("{}/{}.json", FIXTURES_DIR, endpoint);
+    fs::write(&path, serde_json::to_string_pretty(response)? + "\n")?;
+    println!("Wrote {}", path);
+    Ok(())
+}
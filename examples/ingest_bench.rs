@@ -0,0 +1,184 @@
+// Compares writing match stats one row per statement, the way ingestion
+// used to, with the multi-row inserts persist_match_players uses now.
+//
+//   DATABASE_URL=... cargo run --release --example ingest_bench -- [matches]
+//
+// Everything is written to a throwaway ingest_bench schema, which is dropped
+// again at the end, so it can point at the Pi to measure the network cost.
+// Nothing is created outside that schema.
+use cnxns::config::{DbConnection, CONFIG_PATH};
+use cnxns::db::connection::{DbRole, DbSettings};
+use cnxns::db::ingest::{persist_match_players, MatchInfo, PlayerMatch};
+use cnxns::db::migrations::MIGRATIONS;
+use cnxns::db::scrape_status::{set_match_status, MatchStatus, ScrapeSource};
+use cnxns::db::stat_variables::{PlayerStat, StatValue, ValueType, STAT_VARIABLES};
+use dotenv::dotenv;
+use postgres::Client as PgClient;
+use std::env;
+use std::time::{Duration, Instant};
+
+const BENCH_SCHEMA: &str = "ingest_bench";
+const PLAYERS_PER_TEAM: usize = 16;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+    let matches: usize = match env::args().nth(1) {
+        Some(matches) => matches.parse()?,
+        None => 200,
+    };
+
+    let mut settings = DbSettings::load(&DbConnection::load(CONFIG_PATH)?, DbRole::ReadWrite)?;
+    settings.schema = BENCH_SCHEMA.to_string();
+    let mut db_client = settings.connect()?;
+    db_client.batch_execute(&format!("CREATE SCHEMA {}", BENCH_SCHEMA))?;
+
+    let result = create_tables(&mut db_client).and_then(|()| run(&mut db_client, matches));
+    db_client.batch_execute(&format!("DROP SCHEMA {} CASCADE", BENCH_SCHEMA))?;
+    result
+}
+
+// Every migration but the read-only role. A role belongs to the whole
+// cluster, so it would outlive the schema, and creating it needs CREATEROLE.
+fn create_tables(db_client: &mut PgClient) -> Result<(), Box<dyn std::error::Error>> {
+    for migration in MIGRATIONS.iter().filter(|m| m.name != "readonly_role") {
+        db_client.batch_execute(migration.sql)?;
+    }
+    Ok(())
+}
+
+fn run(db_client: &mut PgClient, matches: usize) -> Result<(), Box<dyn std::error::Error>> {
+    db_client.execute(
+        "INSERT INTO league_seasons (league_id, league_name, season_id, number_matches)
+         VALUES ('bench', 'Bench', '2024-2025', $1)",
+        &[&(2 * matches as i32)],
+    )?;
+    let row_by_row: Vec<(MatchInfo, Vec<PlayerMatch>)> =
+        (0..matches).map(|i| synthetic_match("row", i)).collect();
+    let bulk: Vec<(MatchInfo, Vec<PlayerMatch>)> =
+        (0..matches).map(|i| synthetic_match("bulk", i)).collect();
+    for (match_info, _) in row_by_row.iter().chain(&bulk) {
+        db_client.execute(
+            "INSERT INTO matches (league_id, season_id, match_id, home_team_id, away_team_id, fixture_status, scrape_status)
+             VALUES ('bench', '2024-2025', $1, $2, $3, 'completed', 'fetched')",
+            &[&match_info.match_id, &match_info.home_team_id, &match_info.away_team_id],
+        )?;
+    }
+
+    let statements_per_match = 1 + 2 * PLAYERS_PER_TEAM * (STAT_VARIABLES.len() + 2);
+    let started = Instant::now();
+    for (match_info, players) in &row_by_row {
+        write_row_by_row(db_client, match_info, players)?;
+    }
+    let row_by_row_time = started.elapsed();
+    report("row by row", matches, statements_per_match, row_by_row_time);
+
+    let started = Instant::now();
+    for (match_info, players) in &bulk {
+        persist_match_players(db_client, match_info, players, ScrapeSource::Archive)?;
+    }
+    let bulk_time = started.elapsed();
+    report("multi-row", matches, 4, bulk_time);
+
+    println!(
+        "multi-row is {:.1}x faster",
+        row_by_row_time.as_secs_f64() / bulk_time.as_secs_f64()
+    );
+    Ok(())
+}
+
+// The statements ingestion issued before it batched: one per player, one
+// per stat and one per appearance
+fn write_row_by_row(
+    db_client: &mut PgClient,
+    match_info: &MatchInfo,
+    players: &[PlayerMatch],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut transaction = db_client.transaction()?;
+    for player in players {
+        transaction.execute(
+            "INSERT INTO players (player_id, full_name, nationality) VALUES ($1, $2, $3)
+             ON CONFLICT (player_id) DO NOTHING",
+            &[&player.player_id, &player.full_name, &player.nationality],
+        )?;
+        for stat in &player.stats {
+            let (value, value_text) = match &stat.value {
+                StatValue::Number(number) => (Some(*number), None),
+                StatValue::Text(text) => (None, Some(text.as_str())),
+            };
+            transaction.execute(
+                "INSERT INTO player_stats (match_id, team_id, player_id, variable, value, value_text)
+                 VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING",
+                &[
+                    &match_info.match_id,
+                    &player.team_id,
+                    &player.player_id,
+                    &stat.variable,
+                    &value,
+                    &value_text,
+                ],
+            )?;
+        }
+        transaction.execute(
+            "INSERT INTO appearances (match_id, team_id, player_id, minutes, goals, assists)
+             VALUES ($1, $2, $3, 90, 0, 0) ON CONFLICT DO NOTHING",
+            &[&match_info.match_id, &player.team_id, &player.player_id],
+        )?;
+    }
+    set_match_status(
+        &mut transaction,
+        &match_info.match_id,
+        MatchStatus::Ingested,
+        Some(ScrapeSource::Archive),
+    )?;
+    transaction.commit()?;
+    Ok(())
+}
+
+// Two squads that play every match, so most player inserts hit a conflict
+// the way they do in a real season
+fn synthetic_match(prefix: &str, i: usize) -> (MatchInfo, Vec<PlayerMatch>) {
+    let match_info = MatchInfo {
+        match_id: format!("{}-{}", prefix, i),
+        league_id: "bench".to_string(),
+        season_id: "2024-2025".to_string(),
+        home_team_id: format!("{}-home", prefix),
+        home_team_name: "Home".to_string(),
+        away_team_id: format!("{}-away", prefix),
+        away_team_name: "Away".to_string(),
+    };
+
+    let mut players = Vec::new();
+    for team_id in [&match_info.home_team_id, &match_info.away_team_id] {
+        for n in 0..PLAYERS_PER_TEAM {
+            players.push(PlayerMatch {
+                player_id: format!("{}-{}", team_id, n),
+                full_name: format!("Player {} {}", team_id, n),
+                nationality: Some("ENG".to_string()),
+                team_id: team_id.clone(),
+                stats: STAT_VARIABLES
+                    .iter()
+                    .map(|variable| PlayerStat {
+                        variable: variable.variable,
+                        value: match variable.value_type {
+                            ValueType::Text => StatValue::Text("MF".to_string()),
+                            ValueType::Flag => StatValue::Number((n < 11) as u8 as f64),
+                            _ => StatValue::Number(n as f64),
+                        },
+                    })
+                    .collect(),
+            });
+        }
+    }
+    (match_info, players)
+}
+
+fn report(label: &str, matches: usize, statements_per_match: usize, elapsed: Duration) {
+    println!(
+        "{}: {} matches in {:.2}s, {:.1} matches/s, {} statements per match",
+        label,
+        matches,
+        elapsed.as_secs_f64(),
+        matches as f64 / elapsed.as_secs_f64(),
+        statements_per_match
+    );
+}
@@ -1,41 +1,345 @@
-use cnxns::db::api::fixtures::get_leagues_fixtures;
-use cnxns::db::api::generate_key::generate_api_key;
-use cnxns::db::api::matches::get_match_stats;
-use cnxns::db::psql::schema::CREATE_TABLES_SQL;
-use cnxns::db::psql::update_scrape_status::CHECK_SCRAPING_COUNTS;
+use cnxns::config::{config_hash, report_config, Config, CONFIG_PATH};
+use cnxns::db::api::api_key::ApiKey;
+use cnxns::db::api::competitions::sync_competitions;
+use cnxns::db::api::fixtures::{get_leagues_fixtures, refresh_ongoing_fixtures};
+use cnxns::db::api::matches::{get_match_stats, print_match_plan, retry_match};
+use cnxns::db::api::players::enrich_players;
+use cnxns::db::api::reprocess::reprocess_archive;
+use cnxns::db::api::seasons::print_plan;
+use cnxns::db::connection::{DbRole, DbSettings};
+use cnxns::db::filter::ScrapeFilter;
+use cnxns::db::ingest::{derive_player_clubs, get_match_info_by_id, repair_partial_matches};
+use cnxns::db::migrations::require_current_schema;
+use cnxns::db::psql::update_scrape_status::SYNC_SCRAPE_STATUS;
+use cnxns::db::quarantine::{print_quarantined, release_match};
+use cnxns::db::runs::{print_recent_runs, run_job, Job, MatchCounts};
+use cnxns::db::scrape_status::{check_refetch, print_league_season_status};
+use cnxns::db::stat_variables::sync_stat_variables;
 use dotenv::dotenv;
-use postgres::{Client as PgClient, NoTls};
+use postgres::Client as PgClient;
 use std::env;
-use std::fs;
+use std::process;
+
+const USAGE: &str = "usage: api_scraper [command] [options]
+
+commands:
+  (none)                        competitions, fixtures and match stats
+  fixtures                      fixtures only
+  stats                         match stats only
+  status                        where each league season stands, and the last run
+  validate-config | plan | refresh | enrich-players | repair | reprocess | runs
+  quarantine list | quarantine release <match_id> | quarantine retry <match_id>
+
+options, for the full run, fixtures and stats:
+  --league <league_id>          only this configured league
+  --season <season_id>          only this season
+  --limit <n>                   at most n league seasons of fixtures and n matches
+  --match <match_id>            stats only: re-pull this match if it was played
+  --dry-run                     print what would be requested, changing nothing
+status takes --league and --season";
+
+// Runs listed by `api_scraper runs`
+const RECENT_RUNS: i64 = 20;
+
+#[derive(Default)]
+struct Options {
+    filter: ScrapeFilter,
+    match_id: Option<String>,
+    dry_run: bool,
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
-    let content: String = fs::read_to_string("./config.toml").expect("Failed to read config");
-    let config: toml::Value = toml::from_str(&content).expect("Failed to parse config");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (words, options) = parse_args(&args).unwrap_or_else(|e| {
+        println!("{}\n{}", e, USAGE);
+        process::exit(2);
+    });
 
-    _ = fill_db(config);
+    if words.as_slice() == ["validate-config"] {
+        process::exit(if report_config(CONFIG_PATH) { 0 } else { 1 });
+    }
+    let config = Config::load(CONFIG_PATH).unwrap_or_else(|e| {
+        println!("{}", e);
+        process::exit(1);
+    });
+    if let Err(e) = check_options(&words, &options, &config) {
+        println!("{}\n{}", e, USAGE);
+        process::exit(2);
+    }
+    // Recorded with the run, options included
+    let command = if args.is_empty() {
+        "fill".to_string()
+    } else {
+        args.join(" ")
+    };
 
-    Ok(())
+    match words.as_slice() {
+        [] => fill_db(&config, &command, &options),
+        ["fixtures"] => fixtures(&config, &command, &options),
+        ["stats"] => stats(&config, &command, &options),
+        ["status"] => {
+            let mut db_client = connect_db(&config)?;
+            print_league_season_status(&mut db_client, &options.filter)?;
+            print_recent_runs(&mut db_client, 1)
+        }
+        ["plan"] => print_plan(&mut connect_db(&config)?, &config, &options.filter),
+        ["refresh"] => refresh(&config),
+        ["enrich-players"] => scrape_job(&config, "enrich-players", |db_client, _| {
+            enrich_players(db_client, &config)
+        }),
+        ["repair"] => scrape_job(&config, "repair", |db_client, _| repair(db_client)),
+        ["reprocess"] => scrape_job(&config, "reprocess", |db_client, _| {
+            reprocess_archive(db_client, &config)
+        }),
+        ["quarantine", "list"] => print_quarantined(&mut connect_db(&config)?),
+        ["quarantine", "release", match_id] => {
+            scrape_job(&config, "quarantine release", |db_client, _| {
+                if release_match(db_client, match_id)? {
+                    println!("Released match {} from quarantine", match_id);
+                } else {
+                    println!("Match {} is not quarantined", match_id);
+                }
+                Ok(())
+            })
+        }
+        ["quarantine", "retry", match_id] => {
+            scrape_job(&config, "quarantine retry", |db_client, counts| {
+                retry_match(db_client, &config, match_id, counts)
+            })
+        }
+        ["runs"] => print_recent_runs(&mut connect_db(&config)?, RECENT_RUNS),
+        _ => {
+            println!("{}", USAGE);
+            process::exit(2);
+        }
+    }
 }
 
-fn fill_db(config: toml::Value) -> Result<(), Box<dyn std::error::Error>> {
-    if env::var("API_KEY").is_err() {
-        let _ = generate_api_key();
-        return Ok(());
+// Command words first, then --options in any order
+fn parse_args(args: &[String]) -> Result<(Vec<&str>, Options), String> {
+    let mut words = Vec::new();
+    let mut options = Options::default();
+    let mut seen_option = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        seen_option |= arg.starts_with("--");
+        match arg.as_str() {
+            "--dry-run" => options.dry_run = true,
+            "--league" | "--season" | "--match" | "--limit" => {
+                let value = args
+                    .next()
+                    .filter(|value| !value.starts_with("--"))
+                    .ok_or(format!("{} needs a value", arg))?
+                    .clone();
+                match arg.as_str() {
+                    "--league" => options.filter.league_id = Some(value),
+                    "--season" => options.filter.season_id = Some(value),
+                    "--match" => options.match_id = Some(value),
+                    _ => {
+                        let limit =
+                            value
+                                .parse()
+                                .ok()
+                                .filter(|limit| *limit > 0)
+                                .ok_or(format!(
+                                    "--limit must be a positive number, not '{}'",
+                                    value
+                                ))?;
+                        options.filter.limit = Some(limit);
+                    }
+                }
+            }
+            option if option.starts_with("--") => {
+                return Err(format!("Unknown option {}", option));
+            }
+            word if seen_option => {
+                return Err(format!("'{}' must come before the options", word));
+            }
+            word => words.push(word),
+        }
     }
+    Ok((words, options))
+}
+
+fn check_options(words: &[&str], options: &Options, config: &Config) -> Result<(), String> {
+    let scoped = options.filter.league_id.is_some() || options.filter.season_id.is_some();
+    let limited = options.filter.limit.is_some();
+    let accepted = match words {
+        [] | ["fixtures"] | ["stats"] => true,
+        ["status"] | ["plan"] => !limited && options.match_id.is_none() && !options.dry_run,
+        _ => !scoped && !limited && options.match_id.is_none() && !options.dry_run,
+    };
+    if !accepted {
+        return Err(format!("'{}' does not take those options", words.join(" ")));
+    }
+
+    if options.match_id.is_some() {
+        if words != ["stats"] {
+            return Err("--match only applies to stats".to_string());
+        }
+        if scoped || limited {
+            return Err(
+                "--match cannot be combined with --league, --season or --limit".to_string(),
+            );
+        }
+    }
+    if let Some(league_id) = &options.filter.league_id {
+        if !config
+            .fbref_ids
+            .leagues
+            .iter()
+            .any(|league| &league.league_id.to_string() == league_id)
+        {
+            return Err(format!("League {} is not in {}", league_id, CONFIG_PATH));
+        }
+    }
+    Ok(())
+}
 
-    let database_url = env::var("PI_DB_LOCAL").expect("DATABASE_URL must be set");
-    println!("Loaded string {:#?}", database_url);
-    let mut db_client = PgClient::connect(&database_url, NoTls).unwrap();
-    println!("connected");
-    db_client.batch_execute(CREATE_TABLES_SQL).unwrap();
-    db_client.batch_execute(CHECK_SCRAPING_COUNTS).unwrap();
+fn connect_db(config: &Config) -> Result<PgClient, Box<dyn std::error::Error>> {
+    let mut db_client = DbSettings::load(&config.db_connection, DbRole::ReadWrite)?.connect()?;
+    require_current_schema(&mut db_client)?;
+    Ok(db_client)
+}
 
-    println!("Starting fixtures...");
-    get_leagues_fixtures(&mut db_client, &config)?;
-    println!("Fixtures complete, starting match stats...");
-    get_match_stats(&mut db_client, &config)?;
-    println!("Match stats complete");
+// Commands that write to the database hold the scrape lock and are
+// recorded in scrape_runs
+fn scrape_job<F>(config: &Config, command: &str, work: F) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnOnce(&mut PgClient, &mut MatchCounts) -> Result<(), Box<dyn std::error::Error>>,
+{
+    let mut db_client = connect_db(config)?;
+    sync_stat_variables(&mut db_client)?;
+    run_job(
+        &mut db_client,
+        Job::Scrape,
+        command,
+        config_hash(CONFIG_PATH),
+        work,
+    )
+}
 
+fn repair(db_client: &mut PgClient) -> Result<(), Box<dyn std::error::Error>> {
+    let repaired = repair_partial_matches(db_client)?;
+    if repaired.is_empty() {
+        println!("No partially ingested matches found");
+    } else {
+        println!(
+            "Cleared stats for {} partially ingested matches: {}",
+            repaired.len(),
+            repaired.join(", ")
+        );
+    }
     Ok(())
 }
+
+// Keeps ongoing seasons current without walking every configured season
+fn refresh(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    ApiKey::for_config(config)?;
+
+    scrape_job(config, "refresh", |db_client, counts| {
+        repair(db_client)?;
+        db_client.batch_execute(SYNC_SCRAPE_STATUS)?;
+
+        println!("Refreshing ongoing seasons...");
+        refresh_ongoing_fixtures(db_client, config)?;
+        println!("Refresh complete, starting match stats...");
+        get_match_stats(db_client, config, counts, &ScrapeFilter::default())?;
+        println!("Match stats complete");
+        derive_player_clubs(db_client)?;
+
+        Ok(())
+    })
+}
+
+fn fill_db(
+    config: &Config,
+    command: &str,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if options.dry_run {
+        let mut db_client = connect_db(config)?;
+        print_plan(&mut db_client, config, &options.filter)?;
+        println!("Matches already waiting for stats:");
+        return print_match_plan(&mut db_client, &options.filter);
+    }
+    ApiKey::for_config(config)?;
+
+    scrape_job(config, command, |db_client, counts| {
+        repair(db_client)?;
+        db_client.batch_execute(SYNC_SCRAPE_STATUS)?;
+
+        println!("Syncing competitions...");
+        sync_competitions(db_client, config)?;
+        println!("Starting fixtures...");
+        get_leagues_fixtures(db_client, config, &options.filter)?;
+        println!("Fixtures complete, starting match stats...");
+        get_match_stats(db_client, config, counts, &options.filter)?;
+        println!("Match stats complete");
+        derive_player_clubs(db_client)?;
+
+        Ok(())
+    })
+}
+
+fn fixtures(
+    config: &Config,
+    command: &str,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if options.dry_run {
+        return print_plan(&mut connect_db(config)?, config, &options.filter);
+    }
+    ApiKey::for_config(config)?;
+
+    scrape_job(config, command, |db_client, _| {
+        db_client.batch_execute(SYNC_SCRAPE_STATUS)?;
+        println!("Starting fixtures...");
+        get_leagues_fixtures(db_client, config, &options.filter)?;
+        db_client.batch_execute(SYNC_SCRAPE_STATUS)?;
+        println!("Fixtures complete");
+        Ok(())
+    })
+}
+
+fn stats(
+    config: &Config,
+    command: &str,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if options.dry_run {
+        let mut db_client = connect_db(config)?;
+        return match &options.match_id {
+            Some(match_id) => {
+                check_refetch(&mut db_client, match_id)?;
+                let match_info = get_match_info_by_id(&mut db_client, match_id)?;
+                println!(
+                    "Would re-pull {} {}: {} {} v {}",
+                    match_info.league_id,
+                    match_info.season_id,
+                    match_info.match_id,
+                    match_info.home_team_name,
+                    match_info.away_team_name
+                );
+                Ok(())
+            }
+            None => print_match_plan(&mut db_client, &options.filter),
+        };
+    }
+    ApiKey::for_config(config)?;
+
+    scrape_job(config, command, |db_client, counts| {
+        if let Some(match_id) = &options.match_id {
+            retry_match(db_client, config, match_id, counts)?;
+        } else {
+            repair(db_client)?;
+            db_client.batch_execute(SYNC_SCRAPE_STATUS)?;
+            println!("Starting match stats...");
+            get_match_stats(db_client, config, counts, &options.filter)?;
+            println!("Match stats complete");
+        }
+        derive_player_clubs(db_client)?;
+        Ok(())
+    })
+}
@@ -2,9 +2,11 @@ use crate::app::connection_types::PlayerConnection;
 use crate::app::entity_types::{DailyChallenge, Player, Team};
 use crate::app::psql::connections::CHECK_PLAYERS_CONNECTED;
 use crate::app::psql::daily_players::GET_DAILY_PLAYERS;
-use crate::app::psql::search_players::SEARCH_PLAYERS_BY_NAME;
+use crate::app::psql::search_players::{GET_PLAYER_DETAILS, SEARCH_PLAYERS_BY_NAME};
+use crate::db::migrations::check_version;
+use crate::db::psql::migrations::{GET_SCHEMA_VERSION, MIGRATIONS_TABLE_EXISTS};
 use chrono::Local;
-use tokio_postgres::Client;
+use tokio_postgres::{Client, Row};
 
 pub async fn get_challenge_players(
     client: &Client,
@@ -32,16 +34,26 @@ pub async fn get_challenge_players(
         return Err("Required fields cannot be null".into());
     }
 
-    let players = vec![
+    let mut players = [
         Player {
             player_id: player1_id.unwrap(),
             player_name: player1_name.unwrap(),
+            detail: None,
         },
         Player {
             player_id: player2_id.unwrap(),
             player_name: player2_name.unwrap(),
+            detail: None,
         },
     ];
+    for player in players.iter_mut() {
+        if let Some(row) = client
+            .query_opt(GET_PLAYER_DETAILS, &[&player.player_id])
+            .await?
+        {
+            player.detail = player_from_row(&row).detail;
+        }
+    }
 
     Ok(DailyChallenge {
         player1: players[0].clone(),
@@ -59,28 +71,40 @@ pub async fn search_players_by_name(
         .query(SEARCH_PLAYERS_BY_NAME, &[&search_query])
         .await?;
 
-    let players: Vec<Player> = rows
-        .iter()
-        .map(|row| Player {
-            player_id: row.get("player_id"),
-            player_name: row.get("full_name"),
-        })
-        .collect();
+    let players: Vec<Player> = rows.iter().map(player_from_row).collect();
 
     Ok(players)
 }
 
+fn player_from_row(row: &Row) -> Player {
+    Player {
+        player_id: row.get("player_id"),
+        player_name: row.get("full_name"),
+        detail: Player::describe(
+            row.get("position"),
+            row.get("birth_date"),
+            row.get("active"),
+            row.get("last_appearance_date"),
+            row.get("current_club_name"),
+        ),
+    }
+}
+
 pub async fn check_player_connection(
     client: &Client,
     player1_id: String,
     player2_id: String,
+    allow_international: bool,
 ) -> Result<Option<PlayerConnection>, Box<dyn std::error::Error>> {
     if player1_id.is_empty() || player2_id.is_empty() {
         return Err("Player IDs cannot be empty".into());
     }
 
     let rows: Vec<postgres::Row> = client
-        .query(CHECK_PLAYERS_CONNECTED, &[&player1_id, &player2_id])
+        .query(
+            CHECK_PLAYERS_CONNECTED,
+            &[&player1_id, &player2_id, &allow_international],
+        )
         .await?;
 
     if rows.is_empty() {
@@ -102,8 +126,8 @@ pub async fn check_player_connection(
     );
 
     Ok(Some(PlayerConnection {
-        player1_id: player1_id,
-        player2_id: player2_id,
+        player1_id,
+        player2_id,
         matches_together: shared_matches as i32,
         team,
     }))
@@ -112,14 +136,16 @@ pub async fn check_player_connection(
 pub async fn check_game_completion(
     client: &Client,
     new_player_id: &str,
+    allow_international: bool,
 ) -> Result<bool, Box<dyn std::error::Error>> {
-    let starting_state = get_challenge_players(&*client).await.unwrap();
+    let starting_state = get_challenge_players(client).await.unwrap();
     let target_player_id = &starting_state.player2.player_id;
 
     let completion_check = check_player_connection(
         client,
         new_player_id.to_string(),
         target_player_id.to_string(),
+        allow_international,
     )
     .await?;
 
@@ -131,17 +157,35 @@ pub async fn get_player_career(
     player_id: &str,
 ) -> Result<Vec<(String, String, i32)>, Box<dyn std::error::Error>> {
     let rows = client
-        .query(crate::app::psql::search_players::GET_PLAYER_CAREER, &[&player_id])
+        .query(
+            crate::app::psql::search_players::GET_PLAYER_CAREER,
+            &[&player_id],
+        )
         .await?;
 
     let career: Vec<(String, String, i32)> = rows
         .iter()
-        .map(|row| (
-            row.get("team"),
-            row.get("seasons"), 
-            row.get("league_matches")
-        ))
+        .map(|row| (row.get("team"), row.get("seasons"), row.get("matches")))
         .collect();
 
     Ok(career)
-}
\ No newline at end of file
+}
+
+// The server only reads, so it checks the schema version rather than
+// migrating and leaves `migrate up` to whoever deploys it
+pub async fn check_schema_version(client: &Client) -> Result<(), Box<dyn std::error::Error>> {
+    let tracked: bool = client
+        .query_one(MIGRATIONS_TABLE_EXISTS, &[])
+        .await?
+        .get("tracked");
+    let version: i32 = if tracked {
+        client
+            .query_one(GET_SCHEMA_VERSION, &[])
+            .await?
+            .get("version")
+    } else {
+        0
+    };
+    check_version(version)?;
+    Ok(())
+}
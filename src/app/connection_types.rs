@@ -5,6 +5,9 @@ use serde::{Deserialize, Serialize};
 pub struct ConnectionRequest {
     pub player_ids_chain: Vec<String>,
     pub new_player_id: String,
+    // Game modes that count international teammates as links set this
+    #[serde(default)]
+    pub allow_international: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
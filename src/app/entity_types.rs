@@ -1,10 +1,12 @@
 use crate::app::team_data::colors_to_emoji;
+use chrono::{Datelike, NaiveDate};
 use serde::Serialize;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Player {
     pub player_id: String,
     pub player_name: String,
+    pub detail: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -23,6 +25,37 @@ pub struct DailyChallenge {
     pub shortest_route: i32,
 }
 
+impl Player {
+    // A short line to tell players of the same name apart, e.g.
+    // "FW · b. 1992 · Arsenal" or "MF · b. 1972 · Leeds United until 1999"
+    pub fn describe(
+        position: Option<String>,
+        birth_date: Option<NaiveDate>,
+        active: Option<bool>,
+        last_appearance_date: Option<NaiveDate>,
+        current_club: Option<String>,
+    ) -> Option<String> {
+        let club = current_club.map(|club| match (active, last_appearance_date) {
+            (Some(false), Some(date)) => format!("{} until {}", club, date.year()),
+            _ => club,
+        });
+        let parts: Vec<String> = [
+            position,
+            birth_date.map(|date| format!("b. {}", date.year())),
+            club,
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" · "))
+        }
+    }
+}
+
 impl Team {
     pub fn new(
         team_id: String,
@@ -14,8 +14,24 @@ pub async fn home_page(daily_challenge: DailyChallenge) -> Html<String> {
     let html: String = template
         .replace("{{player1_id}}", &daily_challenge.player1.player_id)
         .replace("{{player1_name}}", &daily_challenge.player1.player_name)
+        .replace(
+            "{{player1_detail}}",
+            daily_challenge
+                .player1
+                .detail
+                .as_deref()
+                .unwrap_or_default(),
+        )
         .replace("{{player2_id}}", &daily_challenge.player2.player_id)
         .replace("{{player2_name}}", &daily_challenge.player2.player_name)
+        .replace(
+            "{{player2_detail}}",
+            daily_challenge
+                .player2
+                .detail
+                .as_deref()
+                .unwrap_or_default(),
+        )
         .replace("{{api_prefix}}", api_prefix);
 
     Html(html)
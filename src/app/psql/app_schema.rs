@@ -1,5 +1,6 @@
+// Migration 3, frozen like the other baseline migrations
 pub const CREATE_DAILY_SELECTION_TABLE: &str = r#"
-CREATE TABLE IF NOT EXISTS connections.daily_selection (
+CREATE TABLE IF NOT EXISTS daily_selection (
    date DATE PRIMARY KEY,
    player1_id VARCHAR(80),
    player1_full_name VARCHAR(255),
@@ -8,28 +9,34 @@ CREATE TABLE IF NOT EXISTS connections.daily_selection (
    optimal_distance INT DEFAULT 0,
    created_at_utc TIMESTAMP DEFAULT NOW(),
    updated_at_utc TIMESTAMP DEFAULT NOW(),
-   FOREIGN KEY (player1_id) REFERENCES connections.players(player_id),
-   FOREIGN KEY (player2_id) REFERENCES connections.players(player_id),
+   FOREIGN KEY (player1_id) REFERENCES players(player_id),
+   FOREIGN KEY (player2_id) REFERENCES players(player_id),
    CONSTRAINT different_players CHECK (player1_id != player2_id)
 );
 "#;
 
+// Daily players are drawn from regulars in top-flight domestic leagues, so
+// both are recognisable whichever competitions have been scraped. Only
+// finished seasons count towards being a regular.
 pub const GENERATE_DAILY_SELECTION: &str = r#"
 WITH used_players AS (
-    SELECT player1_id AS player_id FROM connections.daily_selection
+    SELECT player1_id AS player_id FROM daily_selection
     UNION
-    SELECT player2_id AS player_id FROM connections.daily_selection
+    SELECT player2_id AS player_id FROM daily_selection
 ),
 possible_players AS (
-    SELECT p.player_id, p.full_name, COUNT(DISTINCT m.match_id) AS n
-    FROM connections.players p 
-    JOIN connections.player_stats ps ON ps.player_id = p.player_id
-    JOIN connections.matches m ON ps.match_id = m.match_id
-    WHERE ps.variable = 'mins_played'
-      AND m.league_id = '9'
+    SELECT p.player_id, p.full_name, COUNT(*) AS n
+    FROM players p 
+    JOIN appearances a ON a.player_id = p.player_id
+    JOIN matches m ON a.match_id = m.match_id
+    JOIN league_seasons ls ON ls.league_id = m.league_id AND ls.season_id = m.season_id
+    LEFT JOIN competitions c ON c.league_id = m.league_id
+    WHERE ls.end_date < CURRENT_DATE
+      AND COALESCE(c.competition_type, 'domestic_league') = 'domestic_league'
+      AND COALESCE(c.tier, '1st') = '1st'
       AND p.player_id NOT IN (SELECT player_id FROM used_players)
     GROUP BY p.player_id, p.full_name
-    HAVING COUNT(DISTINCT m.match_id) > 100
+    HAVING COUNT(*) > 100
 ),
 random_pair AS (
     SELECT player_id, full_name, ROW_NUMBER() OVER (ORDER BY RANDOM()) as rn
@@ -44,19 +51,20 @@ selected_players AS (
     FROM random_pair
     WHERE rn <= 2
 )
-INSERT INTO connections.daily_selection (date, player1_id, player1_full_name, player2_id, player2_full_name)
+INSERT INTO daily_selection (date, player1_id, player1_full_name, player2_id, player2_full_name)
 SELECT CURRENT_DATE + 1, player1_id, player1_full_name, player2_id, player2_full_name
 FROM selected_players
 WHERE NOT EXISTS (
-    SELECT 1 FROM connections.daily_selection 
+    SELECT 1 FROM daily_selection 
     WHERE date = CURRENT_DATE + 1
 )
 AND player1_id IS NOT NULL 
 AND player2_id IS NOT NULL;
 "#;
 
+// Migration 2, frozen like the other baseline migrations
 pub const CREATE_TEAMS_TABLE: &str = r#"
-CREATE TABLE IF NOT EXISTS connections.teams (
+CREATE TABLE IF NOT EXISTS teams (
     team_id VARCHAR(80) PRIMARY KEY,
     team_name VARCHAR(255),
     common_name VARCHAR(255),
@@ -65,29 +73,143 @@ CREATE TABLE IF NOT EXISTS connections.teams (
     created_at_utc TIMESTAMP DEFAULT NOW(),
     updated_at_utc TIMESTAMP DEFAULT NOW()
 );
+
+ALTER TABLE teams
+    ADD COLUMN IF NOT EXISTS country_code VARCHAR(10),
+    ADD COLUMN IF NOT EXISTS gender VARCHAR(1),
+    ADD COLUMN IF NOT EXISTS canonical_team_id VARCHAR(80);
+
+CREATE TABLE IF NOT EXISTS team_aliases (
+    team_id VARCHAR(80),
+    team_name VARCHAR(255),
+    first_match_date DATE,
+    last_match_date DATE,
+    matches INT,
+    created_at_utc TIMESTAMP DEFAULT NOW(),
+    updated_at_utc TIMESTAMP DEFAULT NOW(),
+    PRIMARY KEY (team_id, team_name)
+);
+
+CREATE TABLE IF NOT EXISTS team_lineage (
+    team_id VARCHAR(80) PRIMARY KEY,
+    canonical_team_id VARCHAR(80) NOT NULL,
+    note VARCHAR(255),
+    created_at_utc TIMESTAMP DEFAULT NOW(),
+    updated_at_utc TIMESTAMP DEFAULT NOW(),
+    CONSTRAINT not_own_lineage CHECK (team_id != canonical_team_id)
+);
+
+CREATE OR REPLACE VIEW team_competitions AS
+SELECT
+    tm.team_id,
+    tm.league_id,
+    c.competition_name,
+    c.country_code,
+    c.gender,
+    c.league_type,
+    (ARRAY_AGG(tm.season_id ORDER BY ls.start_date))[1]::TEXT AS first_season,
+    (ARRAY_AGG(tm.season_id ORDER BY ls.start_date DESC))[1]::TEXT AS last_season,
+    COUNT(*) AS matches
+FROM (
+    SELECT league_id, season_id, home_team_id AS team_id FROM matches
+    UNION ALL
+    SELECT league_id, season_id, away_team_id AS team_id FROM matches
+) tm
+LEFT JOIN league_seasons ls ON ls.league_id = tm.league_id AND ls.season_id = tm.season_id
+LEFT JOIN competitions c ON c.league_id = tm.league_id
+GROUP BY tm.team_id, tm.league_id, c.competition_name, c.country_code, c.gender, c.league_type;
 "#;
 
+// Every name a team has been listed under, with the dates it was used
+pub const REFRESH_TEAM_ALIASES: &str = r#"
+INSERT INTO team_aliases (team_id, team_name, first_match_date, last_match_date, matches)
+SELECT team_id, team_name, MIN(match_date), MAX(match_date), COUNT(*)
+FROM (
+    SELECT home_team_id AS team_id, home_team_name AS team_name, match_date FROM matches
+    UNION ALL
+    SELECT away_team_id AS team_id, away_team_name AS team_name, match_date FROM matches
+) team_names
+GROUP BY team_id, team_name
+ON CONFLICT (team_id, team_name) DO UPDATE SET
+    first_match_date = EXCLUDED.first_match_date,
+    last_match_date = EXCLUDED.last_match_date,
+    matches = EXCLUDED.matches,
+    updated_at_utc = NOW();
+"#;
+
+// Teams take their most recent name. common_name is the name the club, all
+// of its lineage included, has been listed under in the most matches, so
+// one club shows under one name whatever it was called that season; the
+// colours file can still override it. Country and gender come from the
+// competitions a team has played in, domestic leagues first, and
+// canonical_team_id follows team_lineage for clubs FBref lists under more
+// than one id.
 pub const REFRESH_TEAMS_TABLE: &str = r#"
-INSERT INTO connections.teams (team_id, team_name, common_name, colour1, colour2)
-WITH all_teams AS (
-    SELECT DISTINCT home_team_id as team_id, home_team_name as team_name FROM connections.matches
-    UNION
-    SELECT DISTINCT away_team_id as team_id, away_team_name as team_name FROM connections.matches
+INSERT INTO teams (team_id, team_name, common_name, colour1, colour2, country_code, gender, canonical_team_id)
+WITH latest_names AS (
+    SELECT DISTINCT ON (team_id) team_id, team_name
+    FROM team_aliases
+    ORDER BY team_id, last_match_date DESC NULLS LAST, matches DESC
+),
+canonical_names AS (
+    SELECT COALESCE(tl.canonical_team_id, ta.team_id) AS canonical_team_id,
+           ta.team_name,
+           SUM(ta.matches) AS matches,
+           MAX(ta.last_match_date) AS last_match_date
+    FROM team_aliases ta
+    LEFT JOIN team_lineage tl ON tl.team_id = ta.team_id
+    GROUP BY 1, 2
+),
+common_names AS (
+    SELECT DISTINCT ON (canonical_team_id) canonical_team_id, team_name AS common_name
+    FROM canonical_names
+    ORDER BY canonical_team_id, matches DESC, last_match_date DESC NULLS LAST
 ),
-first_teams AS (
-    SELECT team_id, MIN(team_name) as team_name
-    FROM all_teams
-    GROUP BY team_id
+team_countries AS (
+    SELECT DISTINCT ON (team_id) team_id, country_code, gender
+    FROM team_competitions
+    WHERE country_code IS NOT NULL
+    ORDER BY team_id, (league_type = 'domestic_leagues') DESC, matches DESC
 )
-SELECT team_id, team_name, NULL, NULL, NULL
-FROM first_teams
+SELECT ln.team_id, ln.team_name, cn.common_name, NULL, NULL, tc.country_code, tc.gender,
+       COALESCE(tl.canonical_team_id, ln.team_id)
+FROM latest_names ln
+LEFT JOIN team_countries tc ON tc.team_id = ln.team_id
+LEFT JOIN team_lineage tl ON tl.team_id = ln.team_id
+LEFT JOIN common_names cn ON cn.canonical_team_id = COALESCE(tl.canonical_team_id, ln.team_id)
 ON CONFLICT (team_id) DO UPDATE SET
     team_name = EXCLUDED.team_name,
+    common_name = EXCLUDED.common_name,
+    country_code = COALESCE(EXCLUDED.country_code, teams.country_code),
+    gender = COALESCE(EXCLUDED.gender, teams.gender),
+    canonical_team_id = EXCLUDED.canonical_team_id,
     updated_at_utc = NOW();
 "#;
 
+// Names listed under more than one team id that team_lineage does not yet
+// tie together, the usual sign of a club FBref lists again after a
+// refounding or merger
+pub const LIST_LINEAGE_CANDIDATES: &str = r#"
+SELECT ta.team_name,
+       ARRAY_AGG(ta.team_id ORDER BY ta.first_match_date) AS team_ids
+FROM team_aliases ta
+LEFT JOIN team_lineage tl ON tl.team_id = ta.team_id
+GROUP BY ta.team_name
+HAVING COUNT(DISTINCT COALESCE(tl.canonical_team_id, ta.team_id)) > 1
+ORDER BY ta.team_name
+"#;
+
+pub const UPSERT_TEAM_LINEAGE: &str = r#"
+INSERT INTO team_lineage (team_id, canonical_team_id, note)
+VALUES ($1, $2, $3)
+ON CONFLICT (team_id) DO UPDATE SET
+    canonical_team_id = EXCLUDED.canonical_team_id,
+    note = EXCLUDED.note,
+    updated_at_utc = NOW()
+"#;
+
 pub const UPDATE_TEAM_COLORS: &str = r#"
-UPDATE connections.teams 
-SET colour1 = $1, colour2 = $2, updated_at_utc = NOW() 
+UPDATE teams 
+SET colour1 = $1, colour2 = $2, common_name = $4, updated_at_utc = NOW() 
 WHERE team_id = $3
 "#;
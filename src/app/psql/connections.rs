@@ -1,16 +1,30 @@
+// International matches only count as a link when $3 allows them. Matches
+// at lineage-linked ids count together, under the canonical team.
 pub const CHECK_PLAYERS_CONNECTED: &str = r#"
-    SELECT COUNT(*) as shared_matches, shared_team_matches.team_id, t.team_name, t.colour1, t.colour2
+    SELECT COUNT(*) as shared_matches, shared_team_matches.team_id,
+        COALESCE(ct.common_name, ct.team_name, MIN(shared_team_matches.team_name)) AS team_name,
+        ct.colour1,
+        ct.colour2
     FROM (
-        SELECT match_id, team_id
-        FROM connections.player_stats ps 
-        WHERE variable = 'mins_played'
-        AND value > 0
-        AND player_id IN ($1, $2)
-        GROUP BY match_id, team_id
-        HAVING COUNT(DISTINCT player_id) = 2
+        SELECT a1.match_id,
+            COALESCE(t.canonical_team_id, t.team_id) AS team_id,
+            COALESCE(t.common_name, t.team_name) AS team_name
+        FROM appearances a1
+        JOIN appearances a2
+            ON a2.match_id = a1.match_id
+            AND a2.team_id = a1.team_id
+            AND a2.player_id = $2
+            AND a2.player_id != a1.player_id
+            AND a2.minutes > 0
+        JOIN teams t ON t.team_id = a1.team_id
+        JOIN matches m ON m.match_id = a1.match_id
+        LEFT JOIN competitions c ON c.league_id = m.league_id
+        WHERE a1.player_id = $1
+        AND a1.minutes > 0
+        AND ($3 OR c.competition_type IS DISTINCT FROM 'international')
     ) shared_team_matches
-    JOIN connections.teams t ON t.team_id = shared_team_matches.team_id
-    GROUP BY shared_team_matches.team_id, t.team_name, t.colour1, t.colour2
+    LEFT JOIN teams ct ON ct.team_id = shared_team_matches.team_id
+    GROUP BY shared_team_matches.team_id, ct.common_name, ct.team_name, ct.colour1, ct.colour2
     ORDER BY COUNT(*) DESC
     LIMIT 1;
 "#;
@@ -1,5 +1,5 @@
 pub const GET_DAILY_PLAYERS: &str = r#"
     SELECT *
-    FROM connections.daily_selection
+    FROM daily_selection
     WHERE date = $1
 "#;
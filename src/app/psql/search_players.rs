@@ -1,37 +1,67 @@
+// Current players first, so a name shared across eras lists the one people
+// are most likely looking for at the top
 pub const SEARCH_PLAYERS_BY_NAME: &str = r#"
-    SELECT DISTINCT player_id, full_name 
-    FROM connections.players 
-    WHERE LOWER(full_name) LIKE $1 
+    SELECT p.player_id, p.full_name, p.position, p.birth_date, p.active,
+           p.last_appearance_date,
+           COALESCE(ct.common_name, ct.team_name, t.common_name, t.team_name) AS current_club_name
+    FROM players p
+    LEFT JOIN teams t ON t.team_id = p.current_club
+    LEFT JOIN teams ct ON ct.team_id = t.canonical_team_id
+    WHERE LOWER(p.full_name) LIKE $1
+    ORDER BY p.active DESC, p.last_appearance_date DESC NULLS LAST
     LIMIT 10
 "#;
 
+pub const GET_PLAYER_DETAILS: &str = r#"
+    SELECT p.player_id, p.full_name, p.position, p.birth_date, p.active,
+           p.last_appearance_date,
+           COALESCE(ct.common_name, ct.team_name, t.common_name, t.team_name) AS current_club_name
+    FROM players p
+    LEFT JOIN teams t ON t.team_id = p.current_club
+    LEFT JOIN teams ct ON ct.team_id = t.canonical_team_id
+    WHERE p.player_id = $1
+"#;
+
+// Spells are grouped by canonical team under its display name, so a club
+// that changed name or id shows as one line. Matches cover every
+// competition, and national teams show as their own line. Seasons run from
+// the first start year to the last end year, so one calendar-year season
+// reads as 2023 and one split-year season as 2022-2023.
 pub const GET_PLAYER_CAREER: &str = r#"
 WITH player_matches AS (
-    SELECT DISTINCT ps.team_id, ps.match_id
-    FROM connections.player_stats ps
-    WHERE player_id = $1
+    SELECT a.team_id, a.match_id
+    FROM appearances a
+    WHERE a.player_id = $1
 ), player_seasons AS (
     SELECT 
-        m.season_id, 
-        t.team_name,
+        ls.start_year,
+        ls.end_year,
+        ls.start_date,
+        COALESCE(ct.common_name, ct.team_name, t.common_name, t.team_name) AS team_name,
         COUNT(DISTINCT pm.match_id) AS match_count
     FROM player_matches pm
-    JOIN connections.matches m ON m.match_id = pm.match_id
-    JOIN connections.teams t ON t.team_id = pm.team_id
-    GROUP BY m.season_id, t.team_name
+    JOIN matches m ON m.match_id = pm.match_id
+    JOIN league_seasons ls ON ls.league_id = m.league_id AND ls.season_id = m.season_id
+    JOIN teams t ON t.team_id = pm.team_id
+    LEFT JOIN teams ct ON ct.team_id = t.canonical_team_id
+    GROUP BY 1, 2, 3, 4
 ), team_summary AS (
     SELECT 
         team_name,
-        MIN(season_id) AS start_season,
-        MAX(season_id) AS end_season,
+        MIN(start_year) AS start_year,
+        MAX(end_year) AS end_year,
+        MIN(start_date) AS start_date,
         SUM(match_count)::INTEGER AS total_matches
     FROM player_seasons
     GROUP BY team_name
 )
 SELECT 
     team_name AS team,
-    LEFT(start_season, 4) || '-' || RIGHT(end_season, 4) AS seasons,
-    total_matches AS league_matches
+    CASE
+        WHEN start_year = end_year THEN start_year::TEXT
+        ELSE start_year || '-' || end_year
+    END AS seasons,
+    total_matches AS matches
 FROM team_summary
-ORDER BY start_season, team_name
-"#;
\ No newline at end of file
+ORDER BY start_date, team_name
+"#;
@@ -1,8 +1,11 @@
 use crate::app::backend::{
-    check_game_completion, check_player_connection, get_challenge_players, search_players_by_name, get_player_career
+    check_game_completion, check_player_connection, check_schema_version, get_challenge_players,
+    get_player_career, search_players_by_name,
 };
 use crate::app::connection_types::{ConnectionRequest, ConnectionResponse};
 use crate::app::html::home_page;
+use crate::config::{DbConnection, CONFIG_PATH};
+use crate::db::connection::{DbRole, DbSettings};
 use axum::extract::State;
 use axum::{
     extract::Query,
@@ -16,7 +19,7 @@ use dotenv::dotenv;
 use serde::Deserialize;
 use std::env;
 use std::sync::Arc;
-use tokio_postgres::{Client, NoTls};
+use tokio_postgres::Client;
 use tower_http::services::ServeDir;
 
 #[derive(Deserialize)]
@@ -24,22 +27,25 @@ struct SearchQuery {
     q: String,
 }
 
+#[derive(Deserialize)]
+struct GameModeQuery {
+    #[serde(default)]
+    allow_international: bool,
+}
+
 pub async fn run_server() {
     dotenv().ok();
-    let database_url = if env::var("DEPLOYMENT").unwrap_or_default() == "local" {
-        env::var("LOCALHOST_DB_STRING").expect("LOCALHOST_DB_STRING must be set")
-    } else {
-        env::var("PI_DB_STRING").expect("PI_DB_STRING must be set")
-    };
-
-    println!("Using database URL: {}", database_url);
-
-    let (client, connection) = tokio_postgres::connect(&database_url, NoTls).await.unwrap();
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
+    let db_connection = DbConnection::load(CONFIG_PATH).unwrap_or_else(|e| {
+        println!("{}", e);
+        std::process::exit(1);
     });
+    let settings = DbSettings::load(&db_connection, DbRole::ReadOnly).unwrap();
+    let client = settings.connect_async().await.unwrap();
+
+    if let Err(e) = check_schema_version(&client).await {
+        println!("Refusing to start: {}", e);
+        std::process::exit(1);
+    }
 
     let static_service = ServeDir::new("static");
     let client = Arc::new(client);
@@ -100,9 +106,10 @@ async fn connection_handler(
     };
     let new_player_id: String = payload.new_player_id.clone();
     let connection = match check_player_connection(
-        &*client,
+        &client,
         last_player_id.clone(),
         new_player_id.clone(),
+        payload.allow_international,
     )
     .await
     {
@@ -126,18 +133,21 @@ async fn connection_handler(
     let mut updated_chain = payload.player_ids_chain.clone();
     updated_chain.push(new_player_id.clone());
 
-    let is_complete: bool = match check_game_completion(client.as_ref(), &new_player_id).await {
-        Ok(complete) => complete,
-        Err(e) => {
-            println!(
-                "Error checking game completion for {:?}: {}",
-                new_player_id, e
-            );
-            return Json(ConnectionResponse::failure(
-                "Unable to check game completion",
-            ));
-        }
-    };
+    let is_complete: bool =
+        match check_game_completion(client.as_ref(), &new_player_id, payload.allow_international)
+            .await
+        {
+            Ok(complete) => complete,
+            Err(e) => {
+                println!(
+                    "Error checking game completion for {:?}: {}",
+                    new_player_id, e
+                );
+                return Json(ConnectionResponse::failure(
+                    "Unable to check game completion",
+                ));
+            }
+        };
 
     let final_connection_data = if is_complete {
         let starting_state = match get_challenge_players(&client).await {
@@ -153,6 +163,7 @@ async fn connection_handler(
             &client,
             new_player_id.clone(),
             starting_state.player2.player_id,
+            payload.allow_international,
         )
         .await
         {
@@ -172,6 +183,7 @@ async fn connection_handler(
 }
 
 async fn remove_player_handler(
+    Query(mode): Query<GameModeQuery>,
     State(client): State<Arc<Client>>,
     Json(payload): Json<Vec<String>>,
 ) -> Json<ConnectionResponse> {
@@ -183,7 +195,7 @@ async fn remove_player_handler(
     updated_chain.pop();
 
     let is_complete = if let Some(last_player) = updated_chain.last() {
-        check_game_completion(client.as_ref(), last_player)
+        check_game_completion(client.as_ref(), last_player, mode.allow_international)
             .await
             .unwrap_or(false)
     } else {
@@ -218,6 +230,6 @@ async fn career_handler(
             return Err(StatusCode::INTERNAL_SERVER_ERROR);
         }
     };
-    
+
     Ok(Json(career))
-}
\ No newline at end of file
+}
@@ -1,25 +1,70 @@
-use crate::app::psql::app_schema::{CREATE_TEAMS_TABLE, REFRESH_TEAMS_TABLE, UPDATE_TEAM_COLORS};
+use crate::app::psql::app_schema::{
+    LIST_LINEAGE_CANDIDATES, REFRESH_TEAMS_TABLE, REFRESH_TEAM_ALIASES, UPDATE_TEAM_COLORS,
+    UPSERT_TEAM_LINEAGE,
+};
 use csv::Reader;
 use postgres::Client as PgClient;
 use std::collections::HashMap;
 use std::fs::File;
 
+struct TeamDisplay {
+    common_name: String,
+    colour1: String,
+    colour2: String,
+}
+
+struct TeamLineage {
+    team_id: String,
+    canonical_team_id: String,
+    note: Option<String>,
+}
+
 pub fn refresh_teams_table(db_client: &mut PgClient) -> Result<(), Box<dyn std::error::Error>> {
-    db_client.batch_execute(CREATE_TEAMS_TABLE)?;
+    for lineage in parse_team_lineage("static/data/team_lineage.csv")? {
+        db_client.execute(
+            UPSERT_TEAM_LINEAGE,
+            &[&lineage.team_id, &lineage.canonical_team_id, &lineage.note],
+        )?;
+    }
+    db_client.batch_execute(REFRESH_TEAM_ALIASES)?;
     db_client.batch_execute(REFRESH_TEAMS_TABLE)?;
+    print_lineage_candidates(db_client)?;
 
     let color_map = parse_team_colors("static/data/team_colours.csv")?;
 
-    for (team_id, (colour1, colour2)) in color_map {
-        db_client.execute(UPDATE_TEAM_COLORS, &[&colour1, &colour2, &team_id])?;
+    for (team_id, display) in color_map {
+        db_client.execute(
+            UPDATE_TEAM_COLORS,
+            &[
+                &display.colour1,
+                &display.colour2,
+                &team_id,
+                &display.common_name,
+            ],
+        )?;
     }
 
     Ok(())
 }
 
+// Listed so they can be checked and added to team_lineage.csv
+fn print_lineage_candidates(db_client: &mut PgClient) -> Result<(), Box<dyn std::error::Error>> {
+    for row in db_client.query(LIST_LINEAGE_CANDIDATES, &[])? {
+        let team_name: String = row.get("team_name");
+        let team_ids: Vec<String> = row.get("team_ids");
+        println!(
+            "{} is listed under team ids {}, missing from team_lineage.csv?",
+            team_name,
+            team_ids.join(", ")
+        );
+    }
+    Ok(())
+}
+
+// team_name in the colours file is the hand-picked display name for a team
 fn parse_team_colors(
     file_path: &str,
-) -> Result<HashMap<String, (String, String)>, Box<dyn std::error::Error>> {
+) -> Result<HashMap<String, TeamDisplay>, Box<dyn std::error::Error>> {
     let file = File::open(file_path)?;
     let mut reader = Reader::from_reader(file);
     let mut color_map = HashMap::new();
@@ -28,15 +73,42 @@ fn parse_team_colors(
         let record = result?;
         if record.len() >= 4 {
             let team_id = record[0].to_string();
-            let colour1 = record[2].to_string();
-            let colour2 = record[3].to_string();
-            color_map.entry(team_id).or_insert((colour1, colour2));
+            color_map.entry(team_id).or_insert(TeamDisplay {
+                common_name: record[1].to_string(),
+                colour1: record[2].to_string(),
+                colour2: record[3].to_string(),
+            });
         }
     }
 
     Ok(color_map)
 }
 
+// Each row points a team id at the id of the club it is the same club as,
+// e.g. a club FBref lists under a new id after a refounding
+fn parse_team_lineage(file_path: &str) -> Result<Vec<TeamLineage>, Box<dyn std::error::Error>> {
+    let file = File::open(file_path)?;
+    let mut reader = Reader::from_reader(file);
+    let mut lineage = Vec::new();
+
+    for result in reader.records() {
+        let record = result?;
+        if record.len() >= 2 {
+            let note = record
+                .get(2)
+                .filter(|note| !note.is_empty())
+                .map(|note| note.to_string());
+            lineage.push(TeamLineage {
+                team_id: record[0].to_string(),
+                canonical_team_id: record[1].to_string(),
+                note,
+            });
+        }
+    }
+
+    Ok(lineage)
+}
+
 pub fn colors_to_emoji(colour1: &Option<String>, colour2: &Option<String>) -> String {
     let emoji1 = color_to_circle(colour1);
     let emoji2 = color_to_circle(colour2);
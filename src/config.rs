@@ -0,0 +1,516 @@
+use crate::season::Season;
+use scraper::Selector;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+
+pub const CONFIG_PATH: &str = "./config.toml";
+const FULL_SEASONS: &str = "FULL";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub fbref_ids: FbrefIds,
+    pub scraping_config: ScrapingConfig,
+    pub fbref_api: FbrefApi,
+    #[serde(default)]
+    pub db_connection: DbConnection,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FbrefIds {
+    pub base_url: String,
+    pub league_selector_nodes: String,
+    #[serde(rename = "FULL_SEASON_IDS", default)]
+    pub full_season_ids: Vec<String>,
+    pub leagues: Vec<LeagueConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeagueConfig {
+    pub league_name: String,
+    pub league_id: u32,
+    pub season_ids: SeasonIds,
+    // FBref three letter country code, e.g. "ENG", used to pull competition
+    // metadata. Left out for competitions that are not tied to a country.
+    #[serde(default)]
+    pub country_code: Option<String>,
+    // Needed for competitions fbrapi does not list under a country, e.g.
+    // the Champions League or the World Cup
+    #[serde(default)]
+    pub competition_type: Option<CompetitionType>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompetitionType {
+    DomesticLeague,
+    DomesticCup,
+    ContinentalClub,
+    International,
+}
+
+// `season_ids = ["FULL"]` stands in for every season listed in FULL_SEASON_IDS.
+// `{ from = "1992-1993" }` (optionally with `to`) and `{ latest = 3 }` are
+// resolved against the seasons the data source lists for the league.
+#[derive(Debug, Deserialize)]
+#[serde(from = "SeasonIdsToml")]
+pub enum SeasonIds {
+    Full,
+    Listed(Vec<String>),
+    Range { from: String, to: Option<String> },
+    Latest(usize),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SeasonIdsToml {
+    Listed(Vec<String>),
+    Range(SeasonRange),
+    Latest(LatestSeasons),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SeasonRange {
+    from: String,
+    to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LatestSeasons {
+    latest: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScrapingConfig {
+    pub rate_limit_seconds: u64,
+    pub max_retries: u32,
+    // Keep going past matches, and player profiles, whose requests still
+    // fail after max_retries, leaving them for the next run, instead of
+    // stopping the run. Several in a row still stop it, as the source is down.
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FbrefApi {
+    pub rate_limit_ms: u64,
+    // Where the fbrapi key is kept once generated
+    #[serde(default = "default_api_key_file")]
+    pub api_key_file: String,
+}
+
+fn default_api_key_file() -> String {
+    ".fbrapi_key".to_string()
+}
+
+// Connection strings stay in the environment, DATABASE_URL and for the
+// server DATABASE_URL_READONLY; this is everything else about the database
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DbConnection {
+    #[serde(default = "default_schema")]
+    pub schema: String,
+    #[serde(default)]
+    pub tls: TlsMode,
+    pub tls_root_cert: Option<String>,
+    // The old `db_connection = "local"` setting. Accepted so config files
+    // written before DATABASE_URL still load, and warned about as unused.
+    #[serde(default, rename = "db_connection")]
+    pub legacy_db_connection: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsMode {
+    #[default]
+    Disable,
+    Prefer,
+    Require,
+}
+
+// Binaries that only talk to the database read just this table, so the
+// server image does not need the rest of config.toml
+#[derive(Deserialize)]
+struct DbConnectionToml {
+    #[serde(default)]
+    db_connection: DbConnection,
+}
+
+fn default_schema() -> String {
+    "connections".to_string()
+}
+
+#[derive(Debug)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl From<SeasonIdsToml> for SeasonIds {
+    fn from(season_ids: SeasonIdsToml) -> Self {
+        match season_ids {
+            SeasonIdsToml::Listed(season_ids)
+                if season_ids.len() == 1 && season_ids[0] == FULL_SEASONS =>
+            {
+                SeasonIds::Full
+            }
+            SeasonIdsToml::Listed(season_ids) => SeasonIds::Listed(season_ids),
+            SeasonIdsToml::Range(SeasonRange { from, to }) => SeasonIds::Range { from, to },
+            SeasonIdsToml::Latest(LatestSeasons { latest }) => SeasonIds::Latest(latest),
+        }
+    }
+}
+
+impl CompetitionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompetitionType::DomesticLeague => "domestic_league",
+            CompetitionType::DomesticCup => "domestic_cup",
+            CompetitionType::ContinentalClub => "continental_club",
+            CompetitionType::International => "international",
+        }
+    }
+}
+
+impl Default for DbConnection {
+    fn default() -> Self {
+        DbConnection {
+            schema: default_schema(),
+            tls: TlsMode::default(),
+            tls_root_cert: None,
+            legacy_db_connection: None,
+        }
+    }
+}
+
+impl DbConnection {
+    // A missing file means the defaults; anything else wrong with it is an
+    // error like it is for the scrapers
+    pub fn load(path: &str) -> Result<DbConnection, ConfigError> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(ConfigError {
+                    problems: vec![format!("Failed to read {}: {}", path, e)],
+                })
+            }
+        };
+        let parsed: DbConnectionToml = toml::from_str(&content).map_err(|e| ConfigError {
+            problems: vec![format!("Failed to parse {}: {}", path, e)],
+        })?;
+
+        let problems = parsed.db_connection.validate();
+        if !problems.is_empty() {
+            return Err(ConfigError { problems });
+        }
+        Ok(parsed.db_connection)
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let Some(problem) = check_schema_name(&self.schema) {
+            problems.push(format!("db_connection.{}", problem));
+        }
+        if let Some(path) = &self.tls_root_cert {
+            if self.tls == TlsMode::Disable {
+                problems
+                    .push("db_connection.tls_root_cert is set but tls is 'disable'".to_string());
+            } else if fs::metadata(path).is_err() {
+                problems.push(format!("db_connection.tls_root_cert '{}' not found", path));
+            }
+        }
+
+        problems
+    }
+}
+
+impl SeasonIds {
+    // Ranges and latest-N can only be worked out from the seasons the data
+    // source has for the league
+    pub fn needs_discovery(&self) -> bool {
+        matches!(self, SeasonIds::Range { .. } | SeasonIds::Latest(_))
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Invalid config ({} problems):", self.problems.len())?;
+        for problem in &self.problems {
+            writeln!(f, "  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Config, ConfigError> {
+        let content = fs::read_to_string(path).map_err(|e| ConfigError {
+            problems: vec![format!("Failed to read {}: {}", path, e)],
+        })?;
+        let config: Config = toml::from_str(&content).map_err(|e| ConfigError {
+            problems: vec![format!("Failed to parse {}: {}", path, e)],
+        })?;
+
+        let problems = config.validate();
+        if !problems.is_empty() {
+            return Err(ConfigError { problems });
+        }
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let ids = &self.fbref_ids;
+
+        if !ids.base_url.starts_with("http") || !ids.base_url.ends_with('/') {
+            problems.push(format!(
+                "fbref_ids.base_url '{}' must be an http(s) URL ending in '/'",
+                ids.base_url
+            ));
+        }
+        if let Err(e) = Selector::parse(&ids.league_selector_nodes) {
+            problems.push(format!(
+                "fbref_ids.league_selector_nodes '{}' is not a valid CSS selector: {}",
+                ids.league_selector_nodes, e
+            ));
+        }
+        for season_id in &ids.full_season_ids {
+            if let Some(problem) = check_season_id(season_id) {
+                problems.push(format!("fbref_ids.FULL_SEASON_IDS: {}", problem));
+            }
+        }
+
+        if ids.leagues.is_empty() {
+            problems.push("fbref_ids.leagues must contain at least one league".to_string());
+        }
+        let mut league_ids = HashSet::new();
+        for league in &ids.leagues {
+            let label = format!("league '{}' ({})", league.league_name, league.league_id);
+            if !league_ids.insert(league.league_id) {
+                problems.push(format!("{}: league_id is listed more than once", label));
+            }
+            if league.league_name.trim().is_empty() || league.league_name.contains(' ') {
+                problems.push(format!(
+                    "{}: league_name must be the FBref URL slug, e.g. 'Premier-League'",
+                    label
+                ));
+            }
+            if let Some(country_code) = &league.country_code {
+                if country_code.len() != 3 || !country_code.chars().all(|c| c.is_ascii_uppercase())
+                {
+                    problems.push(format!(
+                        "{}: country_code '{}' must be a three letter code, e.g. 'ENG'",
+                        label, country_code
+                    ));
+                }
+            }
+            match &league.season_ids {
+                SeasonIds::Full if ids.full_season_ids.is_empty() => problems.push(format!(
+                    "{}: season_ids is FULL but FULL_SEASON_IDS is empty",
+                    label
+                )),
+                SeasonIds::Full => {}
+                SeasonIds::Range { from, to } => {
+                    for season_id in std::iter::once(from).chain(to) {
+                        if let Some(problem) = check_season_id(season_id) {
+                            problems.push(format!("{}: {}", label, problem));
+                        }
+                    }
+                    let range = (Season::parse(from), to.as_deref().and_then(Season::parse));
+                    if let (Some(from), Some(to)) = range {
+                        if to < from {
+                            problems.push(format!("{}: season_ids 'to' is before 'from'", label));
+                        }
+                    }
+                }
+                SeasonIds::Latest(0) => {
+                    problems.push(format!("{}: season_ids latest must be at least 1", label))
+                }
+                SeasonIds::Latest(_) => {}
+                SeasonIds::Listed(season_ids) => {
+                    if season_ids.is_empty() {
+                        problems.push(format!("{}: season_ids is empty", label));
+                    }
+                    if season_ids
+                        .iter()
+                        .filter_map(|season_id| Season::parse(season_id))
+                        .any(|season| season.format() != season_format(&season_ids[0]))
+                    {
+                        problems.push(format!(
+                            "{}: season_ids mixes split-year and calendar-year seasons",
+                            label
+                        ));
+                    }
+                    for season_id in season_ids {
+                        if season_id == FULL_SEASONS {
+                            problems.push(format!(
+                                "{}: FULL must be the only entry in season_ids",
+                                label
+                            ));
+                        } else if let Some(problem) = check_season_id(season_id) {
+                            problems.push(format!("{}: {}", label, problem));
+                        }
+                    }
+                }
+            }
+        }
+
+        if self.fbref_api.rate_limit_ms == 0 {
+            problems.push("fbref_api.rate_limit_ms must be greater than 0".to_string());
+        }
+        if self.scraping_config.rate_limit_seconds == 0 {
+            problems.push("scraping_config.rate_limit_seconds must be greater than 0".to_string());
+        }
+        problems.extend(self.db_connection.validate());
+
+        problems
+    }
+
+    // Each country code once, in config order
+    pub fn country_codes(&self) -> Vec<&str> {
+        let mut country_codes: Vec<&str> = Vec::new();
+        for league in &self.fbref_ids.leagues {
+            if let Some(country_code) = league.country_code.as_deref() {
+                if !country_codes.contains(&country_code) {
+                    country_codes.push(country_code);
+                }
+            }
+        }
+        country_codes
+    }
+
+    // "split_year" for seasons like 1996-1997, "calendar_year" for seasons
+    // like 2022. None when the seasons are only known after discovery.
+    pub fn season_format(&self, league: &LeagueConfig) -> Option<&'static str> {
+        let season_id = match &league.season_ids {
+            SeasonIds::Full => self.fbref_ids.full_season_ids.first()?,
+            SeasonIds::Listed(season_ids) => season_ids.first()?,
+            SeasonIds::Range { from, .. } => from,
+            SeasonIds::Latest(_) => return None,
+        };
+        Some(season_format(season_id))
+    }
+
+    // The seasons to scrape for a league, oldest first. `available` is what
+    // the data source lists and is only used for ranges and latest-N.
+    pub fn league_seasons(&self, league: &LeagueConfig, available: &[String]) -> Vec<String> {
+        match &league.season_ids {
+            SeasonIds::Full => self.fbref_ids.full_season_ids.clone(),
+            SeasonIds::Listed(season_ids) => season_ids.clone(),
+            SeasonIds::Range { from, to } => {
+                let from = Season::parse(from);
+                let to = to.as_deref().and_then(Season::parse);
+                let mut seasons: Vec<Season> = available
+                    .iter()
+                    .filter_map(|season_id| Season::parse(season_id))
+                    .filter(|season| from.is_some_and(|from| *season >= from))
+                    .filter(|season| to.is_none_or(|to| *season <= to))
+                    .collect();
+                seasons.sort();
+                seasons.iter().map(Season::to_string).collect()
+            }
+            SeasonIds::Latest(latest) => {
+                let mut seasons: Vec<Season> = available
+                    .iter()
+                    .filter_map(|season_id| Season::parse(season_id))
+                    .collect();
+                seasons.sort();
+                seasons
+                    .split_off(seasons.len().saturating_sub(*latest))
+                    .iter()
+                    .map(Season::to_string)
+                    .collect()
+            }
+        }
+    }
+}
+
+fn check_season_id(season_id: &str) -> Option<String> {
+    match Season::parse(season_id) {
+        Some(_) => None,
+        None => Some(format!(
+            "season '{}' must look like 'YYYY-YYYY' with consecutive years or 'YYYY'",
+            season_id
+        )),
+    }
+}
+
+// The schema goes into search_path unquoted, so it is kept to plain lower
+// case identifiers
+pub fn check_schema_name(schema: &str) -> Option<String> {
+    let valid = schema.len() <= 63
+        && schema.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && schema
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    (!valid).then(|| format!("schema '{}' must be a lower case identifier", schema))
+}
+
+// Recorded with each scrape run, so runs can be matched to the config they
+// used. None when the file cannot be read.
+pub fn config_hash(path: &str) -> Option<String> {
+    fs::read(path)
+        .ok()
+        .map(|contents| format!("{:x}", Sha256::digest(contents)))
+}
+
+// Seasons that do not parse are reported by validate and count as split-year
+pub fn season_format(season_id: &str) -> &'static str {
+    Season::parse(season_id).map_or("split_year", |season| season.format())
+}
+
+// Backs the `validate-config` mode of the scraper binaries
+pub fn report_config(path: &str) -> bool {
+    match Config::load(path) {
+        Ok(config) => {
+            let (discovered, listed): (Vec<&LeagueConfig>, Vec<&LeagueConfig>) = config
+                .fbref_ids
+                .leagues
+                .iter()
+                .partition(|league| league.season_ids.needs_discovery());
+            let seasons: usize = listed
+                .iter()
+                .map(|league| config.league_seasons(league, &[]).len())
+                .sum();
+            println!(
+                "{} is valid: {} leagues, {} listed league seasons, {} leagues with seasons discovered from the data source",
+                path,
+                config.fbref_ids.leagues.len(),
+                seasons,
+                discovered.len()
+            );
+            true
+        }
+        Err(e) => {
+            println!("{}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn db_connection_from_before_database_url_still_loads() {
+        let parsed: DbConnectionToml =
+            toml::from_str("[db_connection]\ndb_connection = \"local\"\n").unwrap();
+        let db_connection = parsed.db_connection;
+        assert_eq!(db_connection.legacy_db_connection.as_deref(), Some("local"));
+        assert_eq!(db_connection.schema, "connections");
+        assert!(db_connection.validate().is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::config::Config;
+use crate::db::rate_limit::RateLimiter;
+use crate::db::request::{send_json, RequestError};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+// The key every fbrapi request sends, loaded once per process. It comes
+// from the secrets file, then API_KEY in the environment, and is generated
+// and written to the secrets file when neither has one.
+pub struct ApiKey {
+    path: String,
+    state: Mutex<KeyState>,
+}
+
+struct KeyState {
+    key: String,
+    regenerated: bool,
+}
+
+static API_KEY: OnceLock<ApiKey> = OnceLock::new();
+
+impl ApiKey {
+    pub fn for_config(config: &Config) -> Result<&'static ApiKey, Box<dyn std::error::Error>> {
+        if let Some(api_key) = API_KEY.get() {
+            return Ok(api_key);
+        }
+        let api_key = ApiKey::load(config)?;
+        Ok(API_KEY.get_or_init(|| api_key))
+    }
+
+    fn load(config: &Config) -> Result<ApiKey, Box<dyn std::error::Error>> {
+        let path = config.fbref_api.api_key_file.as_str();
+        let stored = fs::read_to_string(path)
+            .ok()
+            .map(|contents| contents.trim().to_string())
+            .filter(|key| !key.is_empty());
+        let key = match stored {
+            Some(key) => {
+                println!("Using fbrapi API key {} from {}", mask_key(&key), path);
+                key
+            }
+            None => match env::var("API_KEY").ok().filter(|key| !key.is_empty()) {
+                Some(key) => {
+                    println!("Using fbrapi API key {} from API_KEY", mask_key(&key));
+                    key
+                }
+                None => {
+                    let key = generate_api_key(config)?;
+                    store_key(path, &key)?;
+                    println!(
+                        "Generated fbrapi API key {}, saved to {}",
+                        mask_key(&key),
+                        path
+                    );
+                    key
+                }
+            },
+        };
+        Ok(ApiKey {
+            path: path.to_string(),
+            state: Mutex::new(KeyState {
+                key,
+                regenerated: false,
+            }),
+        })
+    }
+
+    pub fn key(&self) -> String {
+        self.lock().key.clone()
+    }
+
+    // Replaces a key fbrapi has rejected, at most once per process so a
+    // run with a key that keeps being refused stops instead of looping.
+    // False when the key was already regenerated.
+    fn regenerate(
+        &self,
+        rejected: &str,
+        config: &Config,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut state = self.lock();
+        if state.key != rejected {
+            return Ok(true);
+        }
+        if state.regenerated {
+            return Ok(false);
+        }
+
+        let key = generate_api_key(config)?;
+        store_key(&self.path, &key)?;
+        println!(
+            "fbrapi rejected API key {}, generated {} and saved it to {}",
+            mask_key(rejected),
+            mask_key(&key),
+            self.path
+        );
+        state.key = key;
+        state.regenerated = true;
+        Ok(true)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, KeyState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Sends an fbrapi request built with the current key. A 401 or 403 gets
+// one new key and one more attempt.
+pub fn send_with_key<F>(config: &Config, build_request: F) -> Result<Value, RequestError>
+where
+    F: Fn(&str) -> RequestBuilder,
+{
+    let api_key = ApiKey::for_config(config)
+        .map_err(|e| RequestError::Unauthorized(format!("No fbrapi API key: {}", e)))?;
+    let rate_limit = RateLimiter::for_config(config).fbrapi();
+    let max_retries = config.scraping_config.max_retries;
+
+    let key = api_key.key();
+    match send_json(build_request(&key), rate_limit, max_retries) {
+        Err(RequestError::Unauthorized(message)) => match api_key.regenerate(&key, config) {
+            Ok(true) => send_json(build_request(&api_key.key()), rate_limit, max_retries),
+            Ok(false) => Err(RequestError::Unauthorized(message)),
+            Err(e) => Err(RequestError::Unauthorized(format!(
+                "{}, and a new API key could not be generated: {}",
+                message, e
+            ))),
+        },
+        result => result,
+    }
+}
+
+// Shows only the last four characters, enough to tell keys apart in logs
+pub fn mask_key(key: &str) -> String {
+    let visible: String = key
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!("****{}", visible)
+}
+
+// Written to a temporary file and renamed into place, readable only by the
+// owner on Unix
+fn store_key(path: &str, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = Path::new(path).parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    let temporary = format!("{}.tmp", path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&temporary)?;
+    writeln!(file, "{}", key)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+// Generates a key for the FBref API
+// https://fbrapi.com/documentation
+fn generate_api_key(config: &Config) -> Result<String, Box<dyn std::error::Error>> {
+    RateLimiter::for_config(config).fbrapi().acquire();
+    let client = Client::new();
+    let response = client
+        .post("https://fbrapi.com/generate_api_key")
+        .send()?
+        .error_for_status()?;
+
+    let json: Value = response.json()?;
+    let api_key = json["api_key"]
+        .as_str()
+        .ok_or("No api_key in fbrapi's response")?
+        .to_string();
+    Ok(api_key)
+}
@@ -0,0 +1,151 @@
+use crate::config::{season_format, CompetitionType, Config};
+use crate::db::api::api_key::send_with_key;
+use crate::db::archive::{archive_response, COMPETITIONS_ENDPOINT};
+use crate::db::psql::competitions::{UPSERT_COMPETITION, UPSERT_CONFIGURED_COMPETITION};
+use crate::db::request::RequestError;
+use postgres::Client as PgClient;
+use reqwest::blocking::Client as APIClient;
+use serde_json::Value;
+
+pub struct Competition {
+    pub league_id: String,
+    pub competition_name: String,
+    pub country_code: Option<String>,
+    pub gender: Option<String>,
+    pub league_type: Option<String>,
+    pub tier: Option<String>,
+    pub competition_type: Option<CompetitionType>,
+    pub season_format: Option<&'static str>,
+}
+
+// Pulls every competition for the configured countries, which is where team
+// country and gender and competition types come from
+pub fn sync_competitions(
+    db_client: &mut PgClient,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let api_client = APIClient::new();
+
+    for country_code in config.country_codes() {
+        println!("Requesting competitions for: {}", country_code);
+        let params = [("country_code", country_code)];
+        let competition_data = match request_competitions(&api_client, &params, config) {
+            Ok(data) => data,
+            Err(e) if e.is_permanent() => {
+                println!("Skipping competitions for {} after {}", country_code, e);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        archive_response(db_client, COMPETITIONS_ENDPOINT, &params, &competition_data)?;
+
+        let competitions = parse_competitions(&competition_data, country_code)?;
+        for competition in &competitions {
+            persist_competition(db_client, competition)?;
+        }
+        println!(
+            "Saved {} competitions for {}",
+            competitions.len(),
+            country_code
+        );
+    }
+
+    for league in &config.fbref_ids.leagues {
+        db_client.execute(
+            UPSERT_CONFIGURED_COMPETITION,
+            &[
+                &league.league_id.to_string(),
+                &league.league_name.replace('-', " "),
+                &league.competition_type.map(|c| c.as_str()),
+                &config.season_format(league),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn request_competitions(
+    api_client: &APIClient,
+    params: &[(&str, &str)],
+    config: &Config,
+) -> Result<Value, RequestError> {
+    send_with_key(config, |api_key| {
+        api_client
+            .get(format!("https://fbrapi.com/{}", COMPETITIONS_ENDPOINT))
+            .header("X-API-Key", api_key)
+            .query(params)
+    })
+}
+
+// Competitions come grouped by league_type, e.g. domestic_leagues or
+// domestic_cups, with numeric league ids and the first and last season held
+pub fn parse_competitions(
+    competition_data: &Value,
+    country_code: &str,
+) -> Result<Vec<Competition>, Box<dyn std::error::Error>> {
+    let groups = competition_data["data"]
+        .as_array()
+        .ok_or("Missing 'data' array in competition_data")?;
+
+    let mut competitions = Vec::new();
+    for group in groups {
+        let league_type = group["league_type"].as_str().map(|s| s.to_string());
+        let competition_type = league_type.as_deref().and_then(competition_type);
+        for league in group["leagues"].as_array().into_iter().flatten() {
+            let league_id = match &league["league_id"] {
+                Value::Number(id) => id.to_string(),
+                Value::String(id) => id.clone(),
+                _ => continue,
+            };
+            competitions.push(Competition {
+                league_id,
+                competition_name: league["competition_name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                country_code: Some(country_code.to_string()),
+                gender: league["gender"].as_str().map(|s| s.to_string()),
+                league_type: league_type.clone(),
+                tier: league["tier"].as_str().map(|s| s.to_string()),
+                competition_type,
+                season_format: league["last_season"]
+                    .as_str()
+                    .or(league["first_season"].as_str())
+                    .map(season_format),
+            });
+        }
+    }
+    Ok(competitions)
+}
+
+fn competition_type(league_type: &str) -> Option<CompetitionType> {
+    match league_type {
+        "domestic_leagues" => Some(CompetitionType::DomesticLeague),
+        "domestic_cups" => Some(CompetitionType::DomesticCup),
+        "international_competitions" | "club_international_cups" => {
+            Some(CompetitionType::ContinentalClub)
+        }
+        "national_team_competitions" => Some(CompetitionType::International),
+        _ => None,
+    }
+}
+
+pub fn persist_competition(
+    db_client: &mut PgClient,
+    competition: &Competition,
+) -> Result<(), Box<dyn std::error::Error>> {
+    db_client.execute(
+        UPSERT_COMPETITION,
+        &[
+            &competition.league_id,
+            &competition.competition_name,
+            &competition.country_code,
+            &competition.gender,
+            &competition.league_type,
+            &competition.tier,
+            &competition.competition_type.map(|c| c.as_str()),
+            &competition.season_format,
+        ],
+    )?;
+    Ok(())
+}
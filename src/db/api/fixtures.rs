@@ -1,139 +1,175 @@
-use chrono::NaiveDate;
+use crate::config::Config;
+use crate::db::api::api_key::send_with_key;
+use crate::db::api::models::parse_fixtures_response;
+use crate::db::api::seasons::planned_seasons;
+use crate::db::archive::{archive_response, FIXTURES_ENDPOINT};
+use crate::db::filter::ScrapeFilter;
+use crate::db::ingest::{
+    check_league_status, persist_league_fixtures, refresh_league_fixtures, Fixture,
+};
+use crate::db::psql::refresh::LIST_ONGOING_SEASONS;
+use crate::db::request::RequestError;
+use crate::db::scrape_status::FixtureStatus;
+use chrono::{Local, NaiveDate};
 use postgres::Client as PgClient;
 use reqwest::blocking::Client as APIClient;
 use serde_json::Value;
-use std::env;
-use std::thread::sleep;
-use std::time::Duration;
 
 pub fn get_leagues_fixtures(
     db_client: &mut PgClient,
-    config: &toml::Value,
+    config: &Config,
+    filter: &ScrapeFilter,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let league_configs = config["fbref_ids"]["leagues"].as_array().unwrap();
-    let full_season_ids = config["fbref_ids"]["FULL_SEASON_IDS"].as_array().unwrap();
-
-    for league in league_configs {
-        let league_id = league["league_id"].as_integer().unwrap().to_string();
-        let league_name = league["league_name"].as_str().unwrap();
-        let season_ids_raw = league["season_ids"].as_array().unwrap();
-        let season_ids = if season_ids_raw.len() == 1 && season_ids_raw[0].as_str() == Some("FULL")
-        {
-            full_season_ids
-        } else {
-            season_ids_raw
-        };
+    let mut skipped = Vec::new();
+    let mut requested = 0;
+    for league in &config.fbref_ids.leagues {
+        let league_id = league.league_id.to_string();
+        let league_name = league.league_name.as_str();
+        if !filter.includes_league(&league_id) {
+            continue;
+        }
 
-        for season in season_ids {
-            if check_league_status(db_client, league_id.clone(), season.as_str().unwrap())? {
-                println!("Skipping: {} for season: {}", league_name, season);
+        for season_id in planned_seasons(db_client, config, league)? {
+            let season_id = season_id.as_str();
+            if !filter.includes(&league_id, season_id) {
                 continue;
             }
-            println!("Requesting: {} for season: {}", league_name, season);
-            let season_id = season.as_str().unwrap();
-
-            let fixtures = request_fixtures(&league_id, season_id)?;
+            if check_league_status(db_client, league_id.clone(), season_id)? {
+                println!("Skipping: {} for season: {}", league_name, season_id);
+                continue;
+            }
+            if !filter.under_limit(requested) {
+                println!("Reached the limit of {} league seasons", requested);
+                break;
+            }
+            requested += 1;
+            println!("Requesting: {} for season: {}", league_name, season_id);
+
+            let params = [("league_id", league_id.as_str()), ("season_id", season_id)];
+            let fixture_data = match request_fixtures(&params, config) {
+                Ok(data) => data,
+                Err(e) if e.is_permanent() => {
+                    println!("Skipping {} {} after {}", league_name, season_id, e);
+                    skipped.push(format!("{} {}", league_name, season_id));
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            archive_response(db_client, FIXTURES_ENDPOINT, &params, &fixture_data)?;
+            let fixtures = parse_fixtures(&fixture_data)?;
             persist_league_fixtures(db_client, &league_id, league_name, season_id, &fixtures)?;
-
-            sleep(Duration::from_millis(
-                config["fbref_api"]["rate_limit_ms"].as_integer().unwrap() as u64,
-            ));
         }
     }
+
+    if !skipped.is_empty() {
+        println!(
+            "Skipped {} league seasons with permanent failures: {}",
+            skipped.len(),
+            skipped.join(", ")
+        );
+    }
     Ok(())
 }
 
-fn check_league_status(
+// Re-fetches fixtures for every season still marked ongoing so new,
+// rescheduled and newly played matches are picked up
+pub fn refresh_ongoing_fixtures(
     db_client: &mut PgClient,
-    league_id: String,
-    season_id: &str,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    let query = "SELECT number_matches, data_count FROM pi_db.connections.league_seasons WHERE league_id = $1 AND season_id = $2";
-    let rows = db_client.query(query, &[&league_id, &season_id])?;
-
-    if let Some(row) = rows.first() {
-        let number_matches: Option<i32> = row.get("number_matches");
-        let data_count: Option<i32> = row.get("data_count");
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let seasons = db_client.query(LIST_ONGOING_SEASONS, &[])?;
+    if seasons.is_empty() {
+        println!("No ongoing seasons to refresh");
+        return Ok(());
+    }
 
-        if let (Some(num_matches), Some(data_cnt)) = (number_matches, data_count) {
-            return Ok(num_matches == data_cnt);
-        }
+    let mut skipped = Vec::new();
+    for row in seasons {
+        let league_id: String = row.get("league_id");
+        let league_name: String = row.get("league_name");
+        let season_id: String = row.get("season_id");
+        println!("Refreshing: {} for season: {}", league_name, season_id);
+
+        let params = [
+            ("league_id", league_id.as_str()),
+            ("season_id", season_id.as_str()),
+        ];
+        let fixture_data = match request_fixtures(&params, config) {
+            Ok(data) => data,
+            Err(e) if e.is_permanent() => {
+                println!("Skipping {} {} after {}", league_name, season_id, e);
+                skipped.push(format!("{} {}", league_name, season_id));
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        archive_response(db_client, FIXTURES_ENDPOINT, &params, &fixture_data)?;
+        let fixtures = parse_fixtures(&fixture_data)?;
+        let summary = refresh_league_fixtures(db_client, &league_id, &season_id, &fixtures)?;
+        println!(
+            "Refreshed {} {}: {} new, {} rescheduled, {} newly played",
+            league_name, season_id, summary.added, summary.rescheduled, summary.newly_played
+        );
     }
 
-    Ok(false)
+    if !skipped.is_empty() {
+        println!(
+            "Skipped {} league seasons with permanent failures: {}",
+            skipped.len(),
+            skipped.join(", ")
+        );
+    }
+    Ok(())
 }
 
-fn request_fixtures(league_id: &str, season_id: &str) -> Result<Value, Box<dyn std::error::Error>> {
-    let api_key = env::var("API_KEY")?;
+fn request_fixtures(params: &[(&str, &str)], config: &Config) -> Result<Value, RequestError> {
     let api_client = APIClient::new();
 
-    let response = api_client
-        .get("https://fbrapi.com/matches")
-        .header("X-API-Key", api_key)
-        .query(&[("league_id", league_id), ("season_id", season_id)])
-        .send()?;
+    send_with_key(config, |api_key| {
+        api_client
+            .get(format!("https://fbrapi.com/{}", FIXTURES_ENDPOINT))
+            .header("X-API-Key", api_key)
+            .query(params)
+    })
+}
 
-    if !response.status().is_success() {
-        return Err(format!("API request failed with status: {}", response.status()).into());
+// fbrapi leaves the score fields null until a match has been played
+pub fn parse_fixtures(fixture_data: &Value) -> Result<Vec<Fixture>, Box<dyn std::error::Error>> {
+    let (response, drift) = parse_fixtures_response(FIXTURES_ENDPOINT, fixture_data)?;
+    drift.warn(FIXTURES_ENDPOINT);
+
+    let today = Local::now().date_naive();
+    let mut fixtures = Vec::with_capacity(response.data.len());
+    for record in response.data {
+        let parsed_date = NaiveDate::parse_from_str(&record.date, "%Y-%m-%d")?;
+        let home_score = record.home_team_score.as_ref().and_then(score);
+        let away_score = record.away_team_score.as_ref().and_then(score);
+        let notes = record.notes.filter(|notes| !notes.is_empty());
+        let scored = record
+            .home_team_score
+            .as_ref()
+            .map(|_| home_score.is_some() && away_score.is_some());
+
+        fixtures.push(Fixture {
+            match_id: record.match_id,
+            home_team_id: record.home_team_id,
+            home_team_name: record.home,
+            away_team_id: record.away_team_id,
+            away_team_name: record.away,
+            match_date: parsed_date,
+            status: FixtureStatus::derive(parsed_date, scored, notes.as_deref(), today),
+            home_score,
+            away_score,
+            notes,
+        });
     }
-
-    let json: Value = response.json()?;
-    Ok(json)
+    Ok(fixtures)
 }
 
-fn persist_league_fixtures(
-    db_client: &mut PgClient,
-    league_id: &str,
-    league_name: &str,
-    season_id: &str,
-    fixture_data: &Value,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let matches = fixture_data["data"].as_array().unwrap();
-
-    db_client.execute(
-        "INSERT INTO pi_db.connections.league_seasons (league_id, league_name, season_id, number_matches) 
-         VALUES ($1, $2, $3, $4) ON CONFLICT (league_id, season_id) DO NOTHING",
-        &[
-            &league_id,
-            &league_name,
-            &season_id,
-            &(matches.len() as i32),
-        ],
-    )?;
-
-    for match_data in matches {
-        let date_str = match_data["date"].as_str().unwrap();
-        let parsed_date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")?;
-
-        match db_client.execute(
-            "INSERT INTO pi_db.connections.matches (league_id, season_id, match_id, home_team_id, home_team_name, away_team_id, away_team_name, match_date, data_count)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) ON CONFLICT DO NOTHING",
-            &[
-                &league_id,
-                &season_id,
-                &match_data["match_id"].as_str().unwrap(),
-                &match_data["home_team_id"].as_str().unwrap(),
-                &match_data["home"].as_str().unwrap(),
-                &match_data["away_team_id"].as_str().unwrap(),
-                &match_data["away"].as_str().unwrap(),
-                &parsed_date,
-                &0i32
-            ]
-        ) {
-            Ok(_) => {},
-            Err(e) => {
-                println!("Failed to insert match {}: {}", match_data["match_id"].as_str().unwrap_or("unknown"), e);
-                println!("Match data: {:#}", match_data);
-                return Err(e.into());
-            }
-        }
+fn score(value: &Value) -> Option<i32> {
+    match value {
+        Value::Number(number) => number.as_i64().map(|n| n as i32),
+        Value::String(raw) => raw.trim().parse().ok(),
+        _ => None,
     }
-
-    println!(
-        "Saved {} matches for {} {}",
-        matches.len(),
-        league_name,
-        season_id
-    );
-    Ok(())
 }
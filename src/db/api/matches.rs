@@ -1,209 +1,261 @@
+use crate::config::Config;
+use crate::db::api::api_key::send_with_key;
+use crate::db::api::models::parse_match_stats_response;
+use crate::db::archive::{archive_response, MATCH_STATS_ENDPOINT};
+use crate::db::filter::ScrapeFilter;
+use crate::db::ingest::{
+    get_match_info, get_match_info_by_id, persist_match_players, replace_match_players, MatchInfo,
+    PlayerMatch,
+};
+use crate::db::pipeline::fetch_and_write;
+use crate::db::quarantine::{is_database_error, record_failure, release_match};
+use crate::db::request::{is_transient, is_unauthorized, RequestError};
+use crate::db::runs::MatchCounts;
+use crate::db::scrape_status::{check_refetch, set_match_status, MatchStatus, ScrapeSource};
+use crate::db::stat_variables::{Source, STAT_VARIABLES};
 use postgres::Client as PgClient;
 use reqwest::blocking::Client as APIClient;
 use serde_json::Value;
-use std::thread::sleep;
-use std::{env, time::Duration};
-
-const PROBLEMATIC_MATCHES: &[&str] = &["19bad36c", "93a55635", "7110621d", "8d12dd69", "ff278feb"];
-
-struct MatchInfo {
-    match_id: String,
-    home_team_id: String,
-    home_team_name: String,
-    away_team_id: String,
-    away_team_name: String,
-}
 
+// The next match is requested while the last one is written
 pub fn get_match_stats(
     db_client: &mut PgClient,
-    config: &toml::Value,
+    config: &Config,
+    counts: &mut MatchCounts,
+    filter: &ScrapeFilter,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let api_key = env::var("API_KEY")?;
     let api_client = APIClient::new();
 
-    let matches = get_match_info(db_client)?;
+    let matches = filter.select_matches(get_match_info(db_client)?);
 
-    for match_info in matches {
-        if PROBLEMATIC_MATCHES.contains(&match_info.match_id.as_str()) {
-            continue;
-        }
-        sleep(Duration::from_millis(
-            config["fbref_api"]["rate_limit_ms"].as_integer().unwrap() as u64,
-        ));
+    fetch_and_write(
+        matches,
+        |match_info| request_match_players(&api_client, &match_info.match_id, config),
+        |match_info, response| {
+            counts.attempt();
+            let result = response
+                .map_err(|e| e.into())
+                .and_then(|match_data| store_match(db_client, &match_info, &match_data, false));
+            match result {
+                Ok(()) => {
+                    println!("Processed match: {}", match_info.match_id);
+                    counts.success();
+                }
+                Err(e) if is_transient(e.as_ref()) => {
+                    counts.source_unavailable();
+                    if !config.scraping_config.continue_on_error
+                        || is_unauthorized(e.as_ref())
+                        || counts.source_down()
+                    {
+                        return Err(format!(
+                            "Failed to request match {}: {}",
+                            match_info.match_id, e
+                        )
+                        .into());
+                    }
+                    println!("Skipping match {} after {}", match_info.match_id, e);
+                }
+                Err(e) if is_database_error(e.as_ref()) => return Err(e),
+                Err(e) => {
+                    println!("Quarantining match {} after {}", match_info.match_id, e);
+                    record_failure(db_client, &match_info.match_id, &e.to_string())?;
+                    counts.failure(&match_info, &e.to_string());
+                }
+            }
+            Ok(())
+        },
+    )
+}
+
+// Backs `stats --dry-run`: the matches a run would request, in order
+pub fn print_match_plan(
+    db_client: &mut PgClient,
+    filter: &ScrapeFilter,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let matches = filter.select_matches(get_match_info(db_client)?);
+    for match_info in &matches {
         println!(
-            "Requesting match: https://fbref.com/en/matches/{}",
-            match_info.match_id
+            "{} {}: {} {} v {}",
+            match_info.league_id,
+            match_info.season_id,
+            match_info.match_id,
+            match_info.home_team_name,
+            match_info.away_team_name
         );
-        let match_data = request_match_players(&api_client, &api_key, &match_info.match_id)
-            .map_err(|e| format!("Failed to request match {}: {}", match_info.match_id, e))?;
-        println!("Got data for match: {}", match_info.match_id);
-
-        process_match_data(&match_data, &match_info, db_client)
-            .map_err(|e| format!("Failed to process match {}: {}", match_info.match_id, e))?;
-        println!("Processed match: {}", match_info.match_id);
     }
+    println!("{} matches to request", matches.len());
     Ok(())
 }
 
-fn get_match_info(db_client: &mut PgClient) -> Result<Vec<MatchInfo>, Box<dyn std::error::Error>> {
-    let rows: Vec<postgres::Row> = db_client.query(
-        "SELECT match_id, home_team_id, home_team_name, away_team_id, away_team_name 
-        FROM pi_db.connections.matches 
-        WHERE match_id NOT IN (SELECT DISTINCT match_id FROM pi_db.connections.player_stats)",
-        &[],
-    )?;
-
-    let matches = rows
-        .iter()
-        .map(|row| MatchInfo {
-            match_id: row.get(0),
-            home_team_id: row.get(1),
-            home_team_name: row.get(2),
-            away_team_id: row.get(3),
-            away_team_name: row.get(4),
-        })
-        .collect();
-    Ok(matches)
-}
-
-fn request_match_players(
-    api_client: &APIClient,
-    api_key: &str,
+// Re-pulls a single match that has been played, whatever else its status,
+// replacing any stats it already has. Used for matches released from
+// quarantine and for broken ones picked out by hand.
+pub fn retry_match(
+    db_client: &mut PgClient,
+    config: &Config,
     match_id: &str,
-) -> Result<Value, Box<dyn std::error::Error>> {
-    println!("Making API request for match: {}", match_id);
-    let response = match api_client
-        .get("https://fbrapi.com/all-players-match-stats")
-        .header("X-API-Key", api_key)
-        .query(&[("match_id", match_id)])
-        .send()
-    {
-        Ok(resp) => resp,
+    counts: &mut MatchCounts,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_refetch(db_client, match_id)?;
+    let api_client = APIClient::new();
+    let match_info = get_match_info_by_id(db_client, match_id)?;
+
+    counts.attempt();
+    let result = request_match_players(&api_client, match_id, config)
+        .map_err(|e| e.into())
+        .and_then(|match_data| store_match(db_client, &match_info, &match_data, true));
+    match result {
+        Ok(()) => {
+            release_match(db_client, match_id)?;
+            println!("Processed match: {}", match_id);
+            counts.success();
+            Ok(())
+        }
+        Err(e) if is_database_error(e.as_ref()) => Err(e),
         Err(e) => {
-            println!("HTTP request failed for match {}: {}", match_id, e);
-            return Err(format!("HTTP request failed for match {}: {}", match_id, e).into());
+            record_failure(db_client, match_id, &e.to_string())?;
+            counts.failure(&match_info, &e.to_string());
+            Err(format!("Retry of match {} failed: {}", match_id, e).into())
         }
-    };
-
-    println!("Got response with status: {}", response.status());
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_body = response
-            .text()
-            .unwrap_or_else(|_| "Could not read error body".to_string());
-        println!("API Error Body: {}", error_body);
-        return Err(format!(
-            "API request failed with status: {} - Body: {}",
-            status, error_body
-        )
-        .into());
     }
-    println!("Parsing JSON response...");
-    let json: Value = response.json()?;
-    println!("Successfully parsed JSON for match: {}", match_id);
-    Ok(json)
 }
 
-fn persist_player_data(
+fn store_match(
     db_client: &mut PgClient,
-    player_data: &Value,
+    match_info: &MatchInfo,
+    match_data: &Value,
+    replace: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let player_id = player_data["meta_data"]["player_id"]
-        .as_str()
-        .ok_or("Missing player_id in meta_data")?;
-    let full_name = player_data["meta_data"]["player_name"]
-        .as_str()
-        .ok_or("Missing player_name in meta_data")?;
-    let nationality: Option<String> = player_data["meta_data"]["player_country_code"]
-        .as_str()
-        .map(|s| s.to_string());
-
-    db_client.execute(
-        "INSERT INTO pi_db.connections.players (player_id, full_name, nationality, current_club, active) 
-         VALUES ($1, $2, $3, $4, $5) 
-         ON CONFLICT (player_id) DO NOTHING",
-        &[
-            &player_id,
-            &full_name,
-            &nationality,
-            &None::<String>,
-            &false,
-        ],
+    println!("Got data for match: {}", match_info.match_id);
+    archive_response(
+        db_client,
+        MATCH_STATS_ENDPOINT,
+        &[("match_id", &match_info.match_id)],
+        match_data,
+    )?;
+    set_match_status(
+        db_client,
+        &match_info.match_id,
+        MatchStatus::Fetched,
+        Some(ScrapeSource::Fbrapi),
     )?;
 
-    Ok(())
-}
-
-fn persist_match_stats(
-    db_client: &mut PgClient,
-    match_info: &MatchInfo,
-    team_name: &str,
-    player_data: &Value,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let player_id = player_data["meta_data"]["player_id"]
-        .as_str()
-        .ok_or("Missing player_id in meta_data")?;
-
-    let team_id = if team_name == match_info.home_team_name {
-        &match_info.home_team_id
-    } else if team_name == match_info.away_team_name {
-        &match_info.away_team_id
+    let players = parse_match_players(match_data, match_info)?;
+    if replace {
+        replace_match_players(db_client, match_info, &players, ScrapeSource::Fbrapi)
     } else {
-        return Err(format!("Team name '{}' not found in match", team_name).into());
-    };
-
-    let summary = &player_data["stats"]["summary"];
-    let mins_played: f64 = summary["min"]
-        .as_str()
-        .unwrap_or("0")
-        .parse()
-        .unwrap_or(0.0);
-    let goals: f64 = summary["gls"].as_f64().unwrap_or(0.0);
-    let assists: f64 = summary["ast"].as_f64().unwrap_or(0.0);
-
-    let stats = [
-        ("mins_played", mins_played),
-        ("goals", goals),
-        ("assists", assists),
-    ];
-
-    for (variable, value) in stats {
-        db_client.execute(
-            "INSERT INTO pi_db.connections.player_stats (match_id, team_id, player_id, variable, value) 
-             VALUES ($1, $2, $3, $4, $5) 
-             ON CONFLICT DO NOTHING",
-            &[
-                &match_info.match_id,
-                &team_id,
-                &player_id,
-                &variable,
-                &value,
-            ],
-        )?;
+        persist_match_players(db_client, match_info, &players, ScrapeSource::Fbrapi)
     }
-    Ok(())
 }
 
-fn process_match_data(
+fn request_match_players(
+    api_client: &APIClient,
+    match_id: &str,
+    config: &Config,
+) -> Result<Value, RequestError> {
+    println!(
+        "Requesting match: https://fbref.com/en/matches/{}",
+        match_id
+    );
+    let json = send_with_key(config, |api_key| {
+        api_client
+            .get(format!("https://fbrapi.com/{}", MATCH_STATS_ENDPOINT))
+            .header("X-API-Key", api_key)
+            .query(&[("match_id", match_id)])
+    })?;
+    println!("Successfully parsed JSON for match: {}", match_id);
+    Ok(json)
+}
+
+pub fn parse_match_players(
     match_data: &Value,
     match_info: &MatchInfo,
-    db_client: &mut PgClient,
-) -> Result<(), Box<dyn std::error::Error>> {
-    for team in match_data["data"]
-        .as_array()
-        .ok_or("Missing 'data' array in match_data")?
-    {
-        let team_name = team["team_name"]
-            .as_str()
-            .ok_or("Missing team_name in team data")?;
-
-        for player in team["players"]
-            .as_array()
-            .ok_or("Missing 'players' array in team data")?
-        {
-            persist_player_data(db_client, player)?;
-            persist_match_stats(db_client, match_info, team_name, player)?;
+) -> Result<Vec<PlayerMatch>, Box<dyn std::error::Error>> {
+    let (response, drift) = parse_match_stats_response(MATCH_STATS_ENDPOINT, match_data)?;
+    drift.warn(MATCH_STATS_ENDPOINT);
+
+    let mut players = Vec::new();
+    for team in response.data {
+        let team_id = match_info.team_id_for(&team.team_name)?;
+
+        for player in team.players {
+            let meta_data = &player.meta_data;
+            let summary = player.stats.summary.as_ref();
+            let stats = STAT_VARIABLES
+                .iter()
+                .filter_map(|stat| {
+                    let value = match stat.api_source {
+                        Source::MetaData(field) => {
+                            meta_data.other.get(field).and_then(|v| stat.from_json(v))
+                        }
+                        Source::Summary(field) => summary
+                            .and_then(|summary| summary.get(field))
+                            .and_then(|v| stat.from_json(v)),
+                        Source::Cell(_) | Source::StartingEleven | Source::Unreported => None,
+                    };
+                    stat.with_default(value)
+                })
+                .collect();
+
+            players.push(PlayerMatch {
+                player_id: player.meta_data.player_id,
+                full_name: player.meta_data.player_name,
+                nationality: player.meta_data.player_country_code,
+                team_id: team_id.to_string(),
+                stats,
+            });
         }
     }
-    Ok(())
+    Ok(players)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::stat_variables::StatValue;
+
+    fn number(player: &PlayerMatch, variable: &str) -> Option<f64> {
+        player
+            .stats
+            .iter()
+            .find(|stat| stat.variable == variable)
+            .map(|stat| match &stat.value {
+                StatValue::Number(value) => *value,
+                StatValue::Text(value) => panic!("{} is text: {}", variable, value),
+            })
+    }
+
+    #[test]
+    fn players_carry_their_team_and_summary_stats() {
+        let match_data: Value = serde_json::from_str(include_str!(
+            "../../../tests/fixtures/fbrapi/all-players-match-stats.json"
+        ))
+        .unwrap();
+        let match_info = MatchInfo {
+            match_id: "cc5b4244".to_string(),
+            league_id: "9".to_string(),
+            season_id: "2023-2024".to_string(),
+            home_team_id: "943e8050".to_string(),
+            home_team_name: "Burnley".to_string(),
+            away_team_id: "b8fd03ef".to_string(),
+            away_team_name: "Manchester City".to_string(),
+        };
+
+        let players = parse_match_players(&match_data, &match_info).unwrap();
+        assert_eq!(players.len(), 4);
+
+        let foster = &players[0];
+        assert_eq!(foster.team_id, "943e8050");
+        assert_eq!(number(foster, "mins_played"), Some(74.0));
+
+        let haaland = &players[2];
+        assert_eq!(haaland.player_id, "1f44ac21");
+        assert_eq!(haaland.nationality.as_deref(), Some("NOR"));
+        assert_eq!(haaland.team_id, "b8fd03ef");
+        assert_eq!(number(haaland, "goals"), Some(2.0));
+        assert_eq!(number(haaland, "xg"), Some(1.6));
+        assert_eq!(number(haaland, "shirt_number"), Some(9.0));
+        // fbrapi does not say who started
+        assert_eq!(number(haaland, "started"), None);
+    }
 }
@@ -1,3 +1,8 @@
+pub mod api_key;
+pub mod competitions;
 pub mod fixtures;
-pub mod generate_key;
 pub mod matches;
+pub mod models;
+pub mod players;
+pub mod reprocess;
+pub mod seasons;
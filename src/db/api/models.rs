@@ -0,0 +1,389 @@
+use crate::db::stat_variables::{Source, STAT_VARIABLES};
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Mutex;
+
+// Typed fbrapi responses. The fields the scraper reads are declared and a
+// missing or mistyped one fails deserialization; everything else is kept
+// in `other` and checked against the fields fbrapi is known to send, so a
+// renamed field shows up as schema drift instead of a panic or a zero.
+
+#[derive(Debug, Deserialize)]
+pub struct FixturesResponse {
+    pub data: Vec<FixtureRecord>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FixtureRecord {
+    pub match_id: String,
+    pub date: String,
+    pub home: String,
+    pub home_team_id: String,
+    pub away: String,
+    pub away_team_id: String,
+    // Absent before fbrapi has a result, null or a number or string after
+    #[serde(default, deserialize_with = "present")]
+    pub home_team_score: Option<Value>,
+    #[serde(default, deserialize_with = "present")]
+    pub away_team_score: Option<Value>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MatchStatsResponse {
+    pub data: Vec<TeamPlayers>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TeamPlayers {
+    pub team_name: String,
+    pub players: Vec<PlayerRecord>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlayerRecord {
+    pub meta_data: PlayerMetaData,
+    #[serde(default)]
+    pub stats: PlayerStatTables,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+// Variables read from meta_data by name, such as player_number, are looked
+// up in `other`
+#[derive(Debug, Deserialize)]
+pub struct PlayerMetaData {
+    pub player_id: String,
+    pub player_name: String,
+    #[serde(default)]
+    pub player_country_code: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+// Only the summary table is read, field by field from STAT_VARIABLES
+#[derive(Debug, Default, Deserialize)]
+pub struct PlayerStatTables {
+    pub summary: Option<Map<String, Value>>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+// Sent by fbrapi but not read, so not reported as drift
+const IGNORED_FIXTURE_FIELDS: &[&str] = &[
+    "time",
+    "wk",
+    "round",
+    "day",
+    "venue",
+    "attendance",
+    "referee",
+    "league_id",
+    "season_id",
+];
+const IGNORED_TEAM_FIELDS: &[&str] = &["home_away"];
+const IGNORED_META_DATA_FIELDS: &[&str] = &["age"];
+const IGNORED_STAT_TABLES: &[&str] = &[
+    "passing",
+    "passing_types",
+    "defense",
+    "possession",
+    "misc",
+    "keepers",
+];
+
+// Keeps a present but null field apart from an absent one
+fn present<'de, D>(deserializer: D) -> Result<Option<Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Value::deserialize(deserializer).map(Some)
+}
+
+// Fields fbrapi added or renamed and fields it stopped sending, by path
+// within the response
+#[derive(Debug, Default)]
+pub struct SchemaDrift {
+    pub unknown: BTreeSet<String>,
+    pub missing: BTreeSet<String>,
+}
+
+// Each drift warning is printed once per run rather than once per response
+static REPORTED_DRIFT: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+impl SchemaDrift {
+    fn check_unknown(&mut self, path: &str, other: &Map<String, Value>, known: &[&str]) {
+        for field in other.keys() {
+            if !known.contains(&field.as_str()) {
+                self.unknown.insert(format!("{}{}", path, field));
+            }
+        }
+    }
+
+    fn check_missing(&mut self, path: &str, present: bool) {
+        if !present {
+            self.missing.insert(path.to_string());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.unknown.is_empty() && self.missing.is_empty()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.unknown.is_empty() {
+            parts.push(format!(
+                "unknown fields {}",
+                self.unknown.iter().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        if !self.missing.is_empty() {
+            parts.push(format!(
+                "missing fields {}",
+                self.missing.iter().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        parts.join("; ")
+    }
+
+    pub fn warn(&self, endpoint: &str) {
+        if self.is_empty() {
+            return;
+        }
+        let warning = format!("Schema drift in {}: {}", endpoint, self.describe());
+        let mut reported = REPORTED_DRIFT.lock().unwrap_or_else(|e| e.into_inner());
+        if reported
+            .get_or_insert_with(HashSet::new)
+            .insert(warning.clone())
+        {
+            println!("{}", warning);
+        }
+    }
+}
+
+fn deserialize<T: for<'de> Deserialize<'de>>(
+    endpoint: &str,
+    response: &Value,
+) -> Result<T, Box<dyn std::error::Error>> {
+    T::deserialize(response).map_err(|e| format!("Schema drift in {}: {}", endpoint, e).into())
+}
+
+pub fn parse_fixtures_response(
+    endpoint: &str,
+    response: &Value,
+) -> Result<(FixturesResponse, SchemaDrift), Box<dyn std::error::Error>> {
+    let fixtures: FixturesResponse = deserialize(endpoint, response)?;
+    let mut drift = SchemaDrift::default();
+    drift.check_unknown("", &fixtures.other, &[]);
+    for fixture in &fixtures.data {
+        drift.check_unknown("data.", &fixture.other, IGNORED_FIXTURE_FIELDS);
+        drift.check_missing("data.home_team_score", fixture.home_team_score.is_some());
+        drift.check_missing("data.away_team_score", fixture.away_team_score.is_some());
+    }
+    Ok((fixtures, drift))
+}
+
+pub fn parse_match_stats_response(
+    endpoint: &str,
+    response: &Value,
+) -> Result<(MatchStatsResponse, SchemaDrift), Box<dyn std::error::Error>> {
+    let match_stats: MatchStatsResponse = deserialize(endpoint, response)?;
+
+    let known_meta_data: Vec<&str> = STAT_VARIABLES
+        .iter()
+        .filter_map(|stat| match stat.api_source {
+            Source::MetaData(field) => Some(field),
+            _ => None,
+        })
+        .chain(IGNORED_META_DATA_FIELDS.iter().copied())
+        .collect();
+    let known_summary: Vec<&str> = STAT_VARIABLES
+        .iter()
+        .filter_map(|stat| match stat.api_source {
+            Source::Summary(field) => Some(field),
+            _ => None,
+        })
+        .collect();
+
+    let mut drift = SchemaDrift::default();
+    drift.check_unknown("", &match_stats.other, &[]);
+    for team in &match_stats.data {
+        drift.check_unknown("data.", &team.other, IGNORED_TEAM_FIELDS);
+        for player in &team.players {
+            drift.check_unknown("data.players.", &player.other, &[]);
+            drift.check_unknown(
+                "data.players.meta_data.",
+                &player.meta_data.other,
+                &known_meta_data,
+            );
+            drift.check_unknown(
+                "data.players.stats.",
+                &player.stats.other,
+                IGNORED_STAT_TABLES,
+            );
+            match &player.stats.summary {
+                Some(summary) => {
+                    drift.check_unknown("data.players.stats.summary.", summary, &known_summary);
+                    for field in &known_summary {
+                        drift.check_missing(
+                            &format!("data.players.stats.summary.{}", field),
+                            summary.contains_key(*field),
+                        );
+                    }
+                }
+                None => drift.check_missing("data.players.stats.summary", false),
+            }
+        }
+    }
+    Ok((match_stats, drift))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::archive::{FIXTURES_ENDPOINT, MATCH_STATS_ENDPOINT};
+
+    // Hand-built in the shape of fbrapi's responses until replaced by a
+    // capture from `cargo run --example capture_fbrapi`
+    const FIXTURES: &str = include_str!("../../../tests/fixtures/fbrapi/matches.json");
+    const MATCH_STATS: &str =
+        include_str!("../../../tests/fixtures/fbrapi/all-players-match-stats.json");
+
+    fn fixtures() -> Value {
+        serde_json::from_str(FIXTURES).unwrap()
+    }
+
+    fn match_stats() -> Value {
+        serde_json::from_str(MATCH_STATS).unwrap()
+    }
+
+    fn first_summary(response: &mut Value) -> &mut Map<String, Value> {
+        response["data"][0]["players"][0]["stats"]["summary"]
+            .as_object_mut()
+            .unwrap()
+    }
+
+    #[test]
+    fn fixtures_parse_without_drift() {
+        let (response, drift) = parse_fixtures_response(FIXTURES_ENDPOINT, &fixtures()).unwrap();
+        assert!(drift.is_empty(), "{}", drift.describe());
+
+        let played = &response.data[0];
+        assert_eq!(played.match_id, "cc5b4244");
+        assert_eq!(played.home_team_id, "943e8050");
+        assert_eq!(played.away, "Manchester City");
+        assert_eq!(played.home_team_score, Some(Value::from(0)));
+        assert_eq!(played.away_team_score, Some(Value::from(3)));
+        // Scores can come as strings
+        assert_eq!(response.data[1].home_team_score, Some(Value::from("2")));
+
+        let postponed = &response.data[2];
+        assert_eq!(postponed.home_team_score, Some(Value::Null));
+        assert_eq!(postponed.notes.as_deref(), Some("Match Postponed"));
+    }
+
+    #[test]
+    fn match_stats_parse_without_drift() {
+        let (response, drift) =
+            parse_match_stats_response(MATCH_STATS_ENDPOINT, &match_stats()).unwrap();
+        assert!(drift.is_empty(), "{}", drift.describe());
+
+        let teams: Vec<&str> = response.data.iter().map(|t| t.team_name.as_str()).collect();
+        assert_eq!(teams, ["Burnley", "Manchester City"]);
+
+        let haaland = &response.data[1].players[0];
+        assert_eq!(haaland.meta_data.player_id, "1f44ac21");
+        assert_eq!(haaland.meta_data.player_name, "Erling Haaland");
+        assert_eq!(
+            haaland.meta_data.player_country_code.as_deref(),
+            Some("NOR")
+        );
+        assert_eq!(haaland.meta_data.other["player_number"], "9");
+        let summary = haaland.stats.summary.as_ref().unwrap();
+        assert_eq!(summary["min"], "90");
+        assert_eq!(summary["gls"], 2);
+        assert_eq!(summary["xg"], 1.6);
+    }
+
+    #[test]
+    fn renamed_summary_field_is_drift() {
+        let mut response = match_stats();
+        let summary = first_summary(&mut response);
+        let goals = summary.remove("gls").unwrap();
+        summary.insert("goals".to_string(), goals);
+
+        let (_, drift) = parse_match_stats_response(MATCH_STATS_ENDPOINT, &response).unwrap();
+        assert_eq!(
+            drift.unknown.iter().collect::<Vec<_>>(),
+            ["data.players.stats.summary.goals"]
+        );
+        assert_eq!(
+            drift.missing.iter().collect::<Vec<_>>(),
+            ["data.players.stats.summary.gls"]
+        );
+    }
+
+    #[test]
+    fn new_fixture_field_is_drift() {
+        let mut response = fixtures();
+        response["data"][0]["kickoff"] = Value::from("20:00");
+
+        let (_, drift) = parse_fixtures_response(FIXTURES_ENDPOINT, &response).unwrap();
+        assert_eq!(drift.unknown.iter().collect::<Vec<_>>(), ["data.kickoff"]);
+        assert!(drift.missing.is_empty());
+    }
+
+    #[test]
+    fn missing_score_is_drift() {
+        let mut response = fixtures();
+        response["data"][1]
+            .as_object_mut()
+            .unwrap()
+            .remove("away_team_score");
+
+        let (_, drift) = parse_fixtures_response(FIXTURES_ENDPOINT, &response).unwrap();
+        assert_eq!(
+            drift.missing.iter().collect::<Vec<_>>(),
+            ["data.away_team_score"]
+        );
+    }
+
+    #[test]
+    fn missing_player_id_fails() {
+        let mut response = match_stats();
+        response["data"][0]["players"][0]["meta_data"]
+            .as_object_mut()
+            .unwrap()
+            .remove("player_id");
+
+        let error = parse_match_stats_response(MATCH_STATS_ENDPOINT, &response)
+            .map(|_| ())
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Schema drift in all-players-match-stats"));
+        assert!(error.contains("player_id"), "{}", error);
+    }
+
+    #[test]
+    fn mistyped_match_id_fails() {
+        let mut response = fixtures();
+        response["data"][0]["match_id"] = Value::from(12345);
+
+        let error = parse_fixtures_response(FIXTURES_ENDPOINT, &response)
+            .map(|_| ())
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Schema drift in matches"), "{}", error);
+    }
+}
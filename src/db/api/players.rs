@@ -0,0 +1,181 @@
+use crate::config::Config;
+use crate::db::api::api_key::send_with_key;
+use crate::db::archive::{archive_response, PLAYERS_ENDPOINT};
+use crate::db::ingest::derive_player_clubs;
+use crate::db::psql::players::{LIST_PLAYERS_TO_ENRICH, UPDATE_PLAYER_PROFILE};
+use crate::db::quarantine::is_database_error;
+use crate::db::request::{is_transient, is_unauthorized, RequestError};
+use crate::db::runs::MAX_CONSECUTIVE_UNAVAILABLE;
+use chrono::NaiveDate;
+use postgres::Client as PgClient;
+use reqwest::blocking::Client as APIClient;
+use serde_json::Value;
+
+pub struct PlayerProfile {
+    pub player_id: String,
+    pub birth_date: Option<NaiveDate>,
+    pub position: Option<String>,
+    pub footed: Option<String>,
+    pub nationality: Option<String>,
+}
+
+// Fetches a profile for every player that does not have one yet, then
+// recomputes current clubs from the latest appearances. As for match stats,
+// a player whose profile cannot be stored is skipped and reported, players
+// not fetched while fbrapi is unavailable are left for the next run, and
+// either fails the run once the rest are done.
+pub fn enrich_players(
+    db_client: &mut PgClient,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let api_client = APIClient::new();
+
+    let players = db_client.query(LIST_PLAYERS_TO_ENRICH, &[])?;
+    println!("Enriching {} players", players.len());
+
+    let mut failed = Vec::new();
+    let mut unavailable = 0;
+    let mut consecutive_unavailable = 0;
+    for row in players {
+        let player_id: String = row.get("player_id");
+        let full_name: String = row.get("full_name");
+
+        let result = request_player_profile(&api_client, &player_id, config)
+            .map_err(|e| e.into())
+            .and_then(|profile_data| {
+                archive_response(
+                    db_client,
+                    PLAYERS_ENDPOINT,
+                    &[("player_id", &player_id)],
+                    &profile_data,
+                )?;
+                let profile = parse_player_profile(&profile_data, &player_id)?;
+                persist_player_profile(db_client, &profile)
+            });
+        match result {
+            Ok(()) => {
+                consecutive_unavailable = 0;
+                println!("Enriched player: {} ({})", full_name, player_id);
+            }
+            Err(e) if is_transient(e.as_ref()) => {
+                unavailable += 1;
+                consecutive_unavailable += 1;
+                if !config.scraping_config.continue_on_error
+                    || is_unauthorized(e.as_ref())
+                    || consecutive_unavailable >= MAX_CONSECUTIVE_UNAVAILABLE
+                {
+                    return Err(format!("Failed to request player {}: {}", player_id, e).into());
+                }
+                println!("Skipping player {} ({}) after {}", full_name, player_id, e);
+            }
+            Err(e) if is_database_error(e.as_ref()) => return Err(e),
+            Err(e) => {
+                consecutive_unavailable = 0;
+                println!("Skipping player {} ({}) after {}", full_name, player_id, e);
+                failed.push(format!("{}: {}", player_id, e));
+            }
+        }
+    }
+
+    let updated = derive_player_clubs(db_client)?;
+    println!("Updated current club for {} players", updated);
+
+    if !failed.is_empty() {
+        println!("{} players failed:", failed.len());
+        for failure in &failed {
+            println!("  {}", failure);
+        }
+    }
+    if unavailable > 0 {
+        println!(
+            "{} players not fetched while fbrapi was unavailable, left for the next run",
+            unavailable
+        );
+    }
+    if failed.is_empty() && unavailable == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} players failed, {} not fetched",
+            failed.len(),
+            unavailable
+        )
+        .into())
+    }
+}
+
+fn request_player_profile(
+    api_client: &APIClient,
+    player_id: &str,
+    config: &Config,
+) -> Result<Value, RequestError> {
+    send_with_key(config, |api_key| {
+        api_client
+            .get(format!("https://fbrapi.com/{}", PLAYERS_ENDPOINT))
+            .header("X-API-Key", api_key)
+            .query(&[("player_id", player_id)])
+    })
+}
+
+// The profile comes back either as the data object itself or as a one
+// element array. positions lists the primary position first.
+pub fn parse_player_profile(
+    profile_data: &Value,
+    player_id: &str,
+) -> Result<PlayerProfile, Box<dyn std::error::Error>> {
+    let profile = match &profile_data["data"] {
+        Value::Array(profiles) => profiles
+            .first()
+            .ok_or(format!("Empty profile for player {}", player_id))?,
+        Value::Object(_) => &profile_data["data"],
+        _ => return Err(format!("Missing 'data' in profile for player {}", player_id).into()),
+    };
+
+    let text = |field: &str| {
+        profile[field]
+            .as_str()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+    };
+    let position = match &profile["positions"] {
+        Value::Array(positions) => positions
+            .first()
+            .and_then(Value::as_str)
+            .map(|position| position.to_string()),
+        _ => text("positions").or_else(|| text("position")),
+    };
+
+    Ok(PlayerProfile {
+        player_id: player_id.to_string(),
+        birth_date: text("date_of_birth")
+            .and_then(|date| NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()),
+        position: position.map(|position| {
+            position
+                .split([',', '-'])
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        }),
+        footed: text("footed").map(|footed| footed.to_lowercase()),
+        nationality: text("nationality"),
+    })
+}
+
+pub fn persist_player_profile(
+    db_client: &mut PgClient,
+    profile: &PlayerProfile,
+) -> Result<(), Box<dyn std::error::Error>> {
+    db_client.execute(
+        UPDATE_PLAYER_PROFILE,
+        &[
+            &profile.player_id,
+            &profile.birth_date,
+            &profile.position,
+            &profile.footed,
+            &profile.nationality,
+        ],
+    )?;
+    Ok(())
+}
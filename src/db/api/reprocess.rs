@@ -0,0 +1,157 @@
+use crate::config::Config;
+use crate::db::api::competitions::{parse_competitions, persist_competition};
+use crate::db::api::fixtures::parse_fixtures;
+use crate::db::api::matches::parse_match_players;
+use crate::db::api::players::{parse_player_profile, persist_player_profile};
+use crate::db::archive::{
+    archived_params, archived_response, COMPETITIONS_ENDPOINT, FIXTURES_ENDPOINT,
+    MATCH_STATS_ENDPOINT, PLAYERS_ENDPOINT,
+};
+use crate::db::ingest::{
+    derive_player_clubs, get_match_info_by_id, persist_league_fixtures, replace_match_players,
+};
+use crate::db::quarantine::{is_database_error, release_match};
+use crate::db::scrape_status::{set_match_status, MatchStatus, ScrapeSource};
+use postgres::Client as PgClient;
+
+// Rebuilds competitions, matches, players, appearances, player_stats and
+// player profiles from the api_responses archive without touching the
+// network. Fixtures go first so every archived match has a matches row to
+// hang its stats off. A response that no longer parses is reported and
+// skipped, but a database error stops the run.
+pub fn reprocess_archive(
+    db_client: &mut PgClient,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut failed = Vec::new();
+
+    let competition_params = archived_params(db_client, COMPETITIONS_ENDPOINT)?;
+    println!(
+        "Reprocessing {} archived competition lists",
+        competition_params.len()
+    );
+    for params in competition_params {
+        let country_code = params["country_code"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+
+        let result = archived_response(db_client, COMPETITIONS_ENDPOINT, &params)?
+            .ok_or("Archived response disappeared".into())
+            .and_then(|response| parse_competitions(&response, &country_code))
+            .and_then(|competitions| {
+                competitions
+                    .iter()
+                    .try_for_each(|competition| persist_competition(db_client, competition))
+            });
+        match result {
+            Ok(()) => {}
+            Err(e) if is_database_error(e.as_ref()) => return Err(e),
+            Err(e) => {
+                println!("Failed to reprocess competitions {}: {}", country_code, e);
+                failed.push(format!("competitions {}", country_code));
+            }
+        }
+    }
+
+    let fixture_params = archived_params(db_client, FIXTURES_ENDPOINT)?;
+    println!(
+        "Reprocessing {} archived fixture lists",
+        fixture_params.len()
+    );
+    for params in fixture_params {
+        let league_id = params["league_id"].as_str().unwrap_or_default().to_string();
+        let season_id = params["season_id"].as_str().unwrap_or_default().to_string();
+        let league_name = config
+            .fbref_ids
+            .leagues
+            .iter()
+            .find(|league| league.league_id.to_string() == league_id)
+            .map(|league| league.league_name.clone())
+            .unwrap_or_else(|| league_id.clone());
+
+        let result = archived_response(db_client, FIXTURES_ENDPOINT, &params)?
+            .ok_or("Archived response disappeared".into())
+            .and_then(|response| parse_fixtures(&response))
+            .and_then(|fixtures| {
+                persist_league_fixtures(db_client, &league_id, &league_name, &season_id, &fixtures)
+            });
+        match result {
+            Ok(()) => {}
+            Err(e) if is_database_error(e.as_ref()) => return Err(e),
+            Err(e) => {
+                println!(
+                    "Failed to reprocess fixtures {} {}: {}",
+                    league_id, season_id, e
+                );
+                failed.push(format!("fixtures {} {}", league_id, season_id));
+            }
+        }
+    }
+
+    let match_params = archived_params(db_client, MATCH_STATS_ENDPOINT)?;
+    println!("Reprocessing {} archived matches", match_params.len());
+    for params in match_params {
+        let match_id = params["match_id"].as_str().unwrap_or_default().to_string();
+
+        let result = get_match_info_by_id(db_client, &match_id).and_then(|match_info| {
+            let response = archived_response(db_client, MATCH_STATS_ENDPOINT, &params)?
+                .ok_or("Archived response disappeared")?;
+            let players = parse_match_players(&response, &match_info)?;
+            set_match_status(
+                db_client,
+                &match_id,
+                MatchStatus::Fetched,
+                Some(ScrapeSource::Archive),
+            )?;
+            replace_match_players(db_client, &match_info, &players, ScrapeSource::Archive)
+        });
+        match result {
+            // A quarantined match rebuilt from its archived response is no
+            // longer quarantined
+            Ok(()) => {
+                release_match(db_client, &match_id)?;
+            }
+            Err(e) if is_database_error(e.as_ref()) => return Err(e),
+            Err(e) => {
+                println!("Failed to reprocess match {}: {}", match_id, e);
+                failed.push(format!("match {}", match_id));
+            }
+        }
+    }
+
+    let player_params = archived_params(db_client, PLAYERS_ENDPOINT)?;
+    println!(
+        "Reprocessing {} archived player profiles",
+        player_params.len()
+    );
+    for params in player_params {
+        let player_id = params["player_id"].as_str().unwrap_or_default().to_string();
+
+        let result = archived_response(db_client, PLAYERS_ENDPOINT, &params)?
+            .ok_or("Archived response disappeared".into())
+            .and_then(|response| parse_player_profile(&response, &player_id))
+            .and_then(|profile| persist_player_profile(db_client, &profile));
+        match result {
+            Ok(()) => {}
+            Err(e) if is_database_error(e.as_ref()) => return Err(e),
+            Err(e) => {
+                println!("Failed to reprocess player {}: {}", player_id, e);
+                failed.push(format!("player {}", player_id));
+            }
+        }
+    }
+    derive_player_clubs(db_client)?;
+
+    if failed.is_empty() {
+        println!("Reprocessing complete");
+        Ok(())
+    } else {
+        Err(format!(
+            "Reprocessing finished with {} failures: {}",
+            failed.len(),
+            failed.join(", ")
+        )
+        .into())
+    }
+}
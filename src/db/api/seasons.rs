@@ -0,0 +1,144 @@
+use crate::config::{Config, LeagueConfig};
+use crate::db::api::api_key::send_with_key;
+use crate::db::archive::{archive_response, SEASONS_ENDPOINT};
+use crate::db::filter::ScrapeFilter;
+use crate::db::ingest::{persist_competition_seasons, stored_competition_seasons};
+use crate::db::psql::update_scrape_status::GET_LEAGUE_SEASON_STATUS;
+use crate::db::request::RequestError;
+use postgres::Client as PgClient;
+use reqwest::blocking::Client as APIClient;
+use serde_json::Value;
+
+// The seasons to scrape for a league. Ranges and latest-N ask fbrapi which
+// seasons exist, falling back to the last list seen if that request fails
+// after its retries.
+pub fn planned_seasons(
+    db_client: &mut PgClient,
+    config: &Config,
+    league: &LeagueConfig,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if !league.season_ids.needs_discovery() {
+        return Ok(config.league_seasons(league, &[]));
+    }
+
+    let league_id = league.league_id.to_string();
+    let params = [("league_id", league_id.as_str())];
+    let available = match request_seasons(&params, config) {
+        Ok(season_data) => {
+            archive_response(db_client, SEASONS_ENDPOINT, &params, &season_data)?;
+            let season_ids = parse_seasons(&season_data)?;
+            persist_competition_seasons(db_client, &league_id, &season_ids)?;
+            season_ids
+        }
+        Err(e) => {
+            let stored = stored_competition_seasons(db_client, &league_id)?;
+            if stored.is_empty() {
+                return Err(format!("No seasons known for {}: {}", league.league_name, e).into());
+            }
+            println!(
+                "Using stored seasons for {} after {}",
+                league.league_name, e
+            );
+            stored
+        }
+    };
+
+    Ok(config.league_seasons(league, &available))
+}
+
+fn request_seasons(params: &[(&str, &str)], config: &Config) -> Result<Value, RequestError> {
+    let api_client = APIClient::new();
+
+    send_with_key(config, |api_key| {
+        api_client
+            .get(format!("https://fbrapi.com/{}", SEASONS_ENDPOINT))
+            .header("X-API-Key", api_key)
+            .query(params)
+    })
+}
+
+pub fn parse_seasons(season_data: &Value) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let seasons = season_data["data"]
+        .as_array()
+        .ok_or("Missing 'data' array in season_data")?;
+
+    Ok(seasons
+        .iter()
+        .filter_map(|season| match &season["season_id"] {
+            Value::String(season_id) => Some(season_id.clone()),
+            Value::Number(season_id) => Some(season_id.to_string()),
+            _ => None,
+        })
+        .collect())
+}
+
+// Backs the `plan` command and `fixtures --dry-run`: every league season
+// the next run would look at and what it would do with it. Read-only, so
+// ranges and latest-N are worked out from the seasons last discovered.
+pub fn print_plan(
+    db_client: &mut PgClient,
+    config: &Config,
+    filter: &ScrapeFilter,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut to_fetch = 0;
+    let mut complete = 0;
+    for league in &config.fbref_ids.leagues {
+        let league_id = league.league_id.to_string();
+        if !filter.includes_league(&league_id) {
+            continue;
+        }
+        let season_ids = if league.season_ids.needs_discovery() {
+            let stored = stored_competition_seasons(db_client, &league_id)?;
+            if stored.is_empty() {
+                println!(
+                    "{}: seasons not discovered yet, the next fixtures run looks them up",
+                    league.league_name
+                );
+                continue;
+            }
+            config.league_seasons(league, &stored)
+        } else {
+            config.league_seasons(league, &[])
+        };
+        if season_ids.is_empty() {
+            println!("{}: no seasons match season_ids", league.league_name);
+        }
+
+        for season_id in season_ids {
+            if !filter.includes(&league_id, &season_id) {
+                continue;
+            }
+            let status =
+                db_client.query_opt(GET_LEAGUE_SEASON_STATUS, &[&league_id, &season_id])?;
+            let (known, ingested, skipped) = status
+                .map(|row| {
+                    (
+                        row.get::<_, i64>("matches_known"),
+                        row.get::<_, i64>("matches_ingested"),
+                        row.get::<_, i64>("matches_skipped"),
+                    )
+                })
+                .unwrap_or_default();
+
+            let action = if known > 0 && known == ingested + skipped {
+                complete += 1;
+                "skip, complete".to_string()
+            } else if !filter.under_limit(to_fetch) {
+                "skip, over the limit".to_string()
+            } else if known == 0 {
+                to_fetch += 1;
+                "fetch, new".to_string()
+            } else {
+                to_fetch += 1;
+                format!("fetch, {} of {} matches ingested", ingested, known)
+            };
+            println!("{} {}: {}", league.league_name, season_id, action);
+        }
+    }
+
+    println!(
+        "{} league seasons to fetch, {} already complete",
+        to_fetch, complete
+    );
+    Ok(())
+}
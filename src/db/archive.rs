@@ -0,0 +1,49 @@
+use crate::db::psql::archive::{ARCHIVE_RESPONSE, GET_ARCHIVED_RESPONSE, LIST_ARCHIVED_PARAMS};
+use postgres::Client as PgClient;
+use serde_json::{Map, Value};
+
+pub const COMPETITIONS_ENDPOINT: &str = "leagues";
+pub const FIXTURES_ENDPOINT: &str = "matches";
+pub const MATCH_STATS_ENDPOINT: &str = "all-players-match-stats";
+pub const PLAYERS_ENDPOINT: &str = "players";
+pub const SEASONS_ENDPOINT: &str = "league-seasons";
+
+// Raw fbrapi responses are kept keyed by endpoint and query parameters so
+// parsing changes can be replayed with `reprocess` instead of re-fetching
+pub fn archive_response(
+    db_client: &mut PgClient,
+    endpoint: &str,
+    params: &[(&str, &str)],
+    response: &Value,
+) -> Result<(), Box<dyn std::error::Error>> {
+    db_client.execute(
+        ARCHIVE_RESPONSE,
+        &[&endpoint, &params_key(params), response],
+    )?;
+    Ok(())
+}
+
+pub fn archived_params(
+    db_client: &mut PgClient,
+    endpoint: &str,
+) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+    let rows = db_client.query(LIST_ARCHIVED_PARAMS, &[&endpoint])?;
+    Ok(rows.iter().map(|row| row.get("params")).collect())
+}
+
+pub fn archived_response(
+    db_client: &mut PgClient,
+    endpoint: &str,
+    params: &Value,
+) -> Result<Option<Value>, Box<dyn std::error::Error>> {
+    let row = db_client.query_opt(GET_ARCHIVED_RESPONSE, &[&endpoint, params])?;
+    Ok(row.map(|row| row.get("response")))
+}
+
+fn params_key(params: &[(&str, &str)]) -> Value {
+    let map: Map<String, Value> = params
+        .iter()
+        .map(|(key, value)| (key.to_string(), Value::String(value.to_string())))
+        .collect();
+    Value::Object(map)
+}
@@ -0,0 +1,183 @@
+use crate::config::{check_schema_name, DbConnection, TlsMode};
+use native_tls::{Certificate, TlsConnector};
+use postgres::Client as PgClient;
+use postgres_native_tls::MakeTlsConnector;
+use std::env;
+use std::fs;
+use tokio_postgres::config::SslMode;
+use tokio_postgres::{Config as PgConfig, NoTls};
+
+pub enum DbRole {
+    ReadWrite,
+    ReadOnly,
+}
+
+// How every binary reaches the database. DATABASE_URL and DATABASE_SCHEMA
+// override the config, so pointing a run at a second database or schema
+// needs nothing else.
+pub struct DbSettings {
+    pub url: String,
+    pub schema: String,
+    pub tls: TlsMode,
+    pub tls_root_cert: Option<String>,
+    pub role: DbRole,
+}
+
+// Where the connection string came from before DATABASE_URL. No longer
+// read, only named in errors and warnings so an old deployment is told
+// what to change.
+const LEGACY_URL_VARS: &[&str] = &["PI_DB_LOCAL", "PI_DB_STRING", "LOCALHOST_DB_STRING"];
+
+impl DbSettings {
+    // The server connects as the read-only role when DATABASE_URL_READONLY
+    // is set. Its sessions are read-only either way.
+    pub fn load(
+        db_connection: &DbConnection,
+        role: DbRole,
+    ) -> Result<DbSettings, Box<dyn std::error::Error>> {
+        let legacy_vars: Vec<&str> = LEGACY_URL_VARS
+            .iter()
+            .copied()
+            .filter(|name| env::var(name).is_ok())
+            .collect();
+        let url = match role {
+            DbRole::ReadOnly => env::var("DATABASE_URL_READONLY")
+                .or_else(|_| env::var("DATABASE_URL"))
+                .map_err(|_| missing_url("DATABASE_URL_READONLY or DATABASE_URL", &legacy_vars))?,
+            DbRole::ReadWrite => {
+                env::var("DATABASE_URL").map_err(|_| missing_url("DATABASE_URL", &legacy_vars))?
+            }
+        };
+        for warning in legacy_warnings(db_connection, &legacy_vars) {
+            println!("{}", warning);
+        }
+        let schema = env::var("DATABASE_SCHEMA").unwrap_or_else(|_| db_connection.schema.clone());
+        if let Some(problem) = check_schema_name(&schema) {
+            return Err(format!("DATABASE_SCHEMA {}", problem).into());
+        }
+
+        Ok(DbSettings {
+            url,
+            schema,
+            tls: db_connection.tls,
+            tls_root_cert: db_connection.tls_root_cert.clone(),
+            role,
+        })
+    }
+
+    fn pg_config(&self) -> Result<PgConfig, Box<dyn std::error::Error>> {
+        let mut config: PgConfig = self.url.parse()?;
+        let mut options = format!("-c search_path={}", self.schema);
+        if let DbRole::ReadOnly = self.role {
+            options.push_str(" -c default_transaction_read_only=on");
+        }
+        config.options(&options);
+        config.ssl_mode(match self.tls {
+            TlsMode::Disable => SslMode::Disable,
+            TlsMode::Prefer => SslMode::Prefer,
+            TlsMode::Require => SslMode::Require,
+        });
+        Ok(config)
+    }
+
+    fn tls_connector(&self) -> Result<MakeTlsConnector, Box<dyn std::error::Error>> {
+        let mut builder = TlsConnector::builder();
+        if let Some(path) = &self.tls_root_cert {
+            builder.add_root_certificate(Certificate::from_pem(&fs::read(path)?)?);
+        }
+        Ok(MakeTlsConnector::new(builder.build()?))
+    }
+
+    pub fn connect(&self) -> Result<PgClient, Box<dyn std::error::Error>> {
+        let config = postgres::Config::from(self.pg_config()?);
+        let client = match self.tls {
+            TlsMode::Disable => config.connect(NoTls)?,
+            _ => config.connect(self.tls_connector()?)?,
+        };
+        println!("Connected to {}", self.describe());
+        Ok(client)
+    }
+
+    pub async fn connect_async(
+        &self,
+    ) -> Result<tokio_postgres::Client, Box<dyn std::error::Error>> {
+        let config = self.pg_config()?;
+        let client = match self.tls {
+            TlsMode::Disable => {
+                let (client, connection) = config.connect(NoTls).await?;
+                tokio::spawn(async move {
+                    if let Err(e) = connection.await {
+                        eprintln!("connection error: {}", e);
+                    }
+                });
+                client
+            }
+            _ => {
+                let (client, connection) = config.connect(self.tls_connector()?).await?;
+                tokio::spawn(async move {
+                    if let Err(e) = connection.await {
+                        eprintln!("connection error: {}", e);
+                    }
+                });
+                client
+            }
+        };
+        println!("Connected to {}", self.describe());
+        Ok(client)
+    }
+
+    // For logs, so the password never ends up in them
+    pub fn describe(&self) -> String {
+        let config = match self.pg_config() {
+            Ok(config) => config,
+            Err(_) => return format!("schema {}", self.schema),
+        };
+        let host = config
+            .get_hosts()
+            .iter()
+            .map(|host| match host {
+                tokio_postgres::config::Host::Tcp(host) => host.clone(),
+                #[cfg(unix)]
+                tokio_postgres::config::Host::Unix(path) => path.display().to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "{}@{}/{} schema {}",
+            config.get_user().unwrap_or_default(),
+            host,
+            config.get_dbname().unwrap_or_default(),
+            self.schema
+        )
+    }
+}
+
+fn missing_url(names: &str, legacy_vars: &[&str]) -> String {
+    if legacy_vars.is_empty() {
+        format!("{} must be set", names)
+    } else {
+        format!(
+            "{} must be set, replacing {} (no longer read)",
+            names,
+            legacy_vars.join(", ")
+        )
+    }
+}
+
+fn legacy_warnings(db_connection: &DbConnection, legacy_vars: &[&str]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Some(value) = &db_connection.legacy_db_connection {
+        warnings.push(format!(
+            "db_connection.db_connection = \"{}\" is no longer read and can be removed; \
+             the database comes from DATABASE_URL",
+            value
+        ));
+    }
+    if !legacy_vars.is_empty() {
+        warnings.push(format!(
+            "{} set but no longer read, the database comes from DATABASE_URL",
+            legacy_vars.join(", ")
+        ));
+    }
+    warnings
+}
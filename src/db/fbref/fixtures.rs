@@ -0,0 +1,208 @@
+use crate::config::Config;
+use crate::db::fbref::page::{
+    build_client, cell_link, cell_text, fetch_page, id_from_href, selector,
+};
+use crate::db::fbref::seasons::planned_seasons;
+use crate::db::ingest::{check_league_status, persist_league_fixtures, Fixture};
+use crate::db::scrape_status::FixtureStatus;
+use chrono::{Local, NaiveDate};
+use postgres::Client as PgClient;
+use scraper::Html;
+
+pub fn get_leagues_fixtures(
+    db_client: &mut PgClient,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let base_url = &config.fbref_ids.base_url;
+    let link_selector = &config.fbref_ids.league_selector_nodes;
+    let html_client = build_client()?;
+
+    let mut skipped = Vec::new();
+    for league in &config.fbref_ids.leagues {
+        let league_id = league.league_id.to_string();
+        let league_name = league.league_name.as_str();
+
+        for season_id in planned_seasons(db_client, &html_client, config, league)? {
+            let season_id = season_id.as_str();
+            if check_league_status(db_client, league_id.clone(), season_id)? {
+                println!("Skipping: {} for season: {}", league_name, season_id);
+                continue;
+            }
+
+            let url = schedule_url(base_url, &league_id, league_name, season_id);
+            println!("Requesting: {}", url);
+            let page = match fetch_page(&html_client, &url, config) {
+                Ok(page) => page,
+                Err(e) if e.is_permanent() => {
+                    println!("Skipping {} {} after {}", league_name, season_id, e);
+                    skipped.push(format!("{} {}", league_name, season_id));
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let fixtures = parse_schedule(&page, link_selector)?;
+            persist_league_fixtures(db_client, &league_id, league_name, season_id, &fixtures)?;
+        }
+    }
+
+    if !skipped.is_empty() {
+        println!(
+            "Skipped {} league seasons with permanent failures: {}",
+            skipped.len(),
+            skipped.join(", ")
+        );
+    }
+    Ok(())
+}
+
+// e.g. https://fbref.com/en/comps/9/1996-1997/schedule/1996-1997-Premier-League-Scores-and-Fixtures
+pub fn schedule_url(base_url: &str, league_id: &str, league_name: &str, season_id: &str) -> String {
+    format!(
+        "{}comps/{}/{}/schedule/{}-{}-Scores-and-Fixtures",
+        base_url, league_id, season_id, season_id, league_name
+    )
+}
+
+// Reads every fixture row that links to a match page. The score cell reads
+// e.g. "2–1" once a match is played, and the notes cell flags postponed and
+// abandoned matches.
+pub fn parse_schedule(
+    page: &str,
+    link_selector: &str,
+) -> Result<Vec<Fixture>, Box<dyn std::error::Error>> {
+    let document = Html::parse_document(page);
+    let row_selector = selector("table.stats_table tbody tr")?;
+    let report_selector = selector(link_selector)?;
+
+    let today = Local::now().date_naive();
+    let mut fixtures = Vec::new();
+    for row in document.select(&row_selector) {
+        let match_id = match row
+            .select(&report_selector)
+            .filter_map(|link| link.value().attr("href"))
+            .find_map(|href| id_from_href(href, "/matches/"))
+        {
+            Some(id) => id,
+            None => continue,
+        };
+
+        let date_str =
+            cell_text(&row, "date").ok_or(format!("Missing date for match {}", match_id))?;
+        let match_date = NaiveDate::parse_from_str(&date_str, "%Y-%m-%d")?;
+        let (home_score, away_score) = cell_text(&row, "score")
+            .and_then(|score| parse_score(&score))
+            .unzip();
+        let notes = cell_text(&row, "notes").filter(|notes| !notes.is_empty());
+        let (home_href, home_team_name) = cell_link(&row, "home_team")
+            .ok_or(format!("Missing home team for match {}", match_id))?;
+        let (away_href, away_team_name) = cell_link(&row, "away_team")
+            .ok_or(format!("Missing away team for match {}", match_id))?;
+
+        fixtures.push(Fixture {
+            home_team_id: id_from_href(&home_href, "/squads/")
+                .ok_or(format!("Missing home team id for match {}", match_id))?,
+            away_team_id: id_from_href(&away_href, "/squads/")
+                .ok_or(format!("Missing away team id for match {}", match_id))?,
+            home_team_name,
+            away_team_name,
+            status: FixtureStatus::derive(
+                match_date,
+                Some(home_score.is_some()),
+                notes.as_deref(),
+                today,
+            ),
+            match_date,
+            home_score,
+            away_score,
+            notes,
+            match_id,
+        });
+    }
+    Ok(fixtures)
+}
+
+// Scores use an en dash and can carry penalty shootouts, e.g. "(4) 1–1 (3)"
+fn parse_score(score: &str) -> Option<(i32, i32)> {
+    let mut goals = score
+        .split(['–', '-'])
+        .map(|side| side.split_whitespace().find(|part| !part.starts_with('(')));
+    let home = goals.next()??.parse().ok()?;
+    let away = goals.next()??.parse().ok()?;
+    Some((home, away))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINK_SELECTOR: &str = "td[data-stat='match_report'] a";
+    const LEAGUE_PAGE: &str =
+        include_str!("../../../tests/fixtures/fbref/schedule_premier_league.html");
+    const CUP_PAGE: &str = include_str!("../../../tests/fixtures/fbref/schedule_fa_cup.html");
+
+    fn fixture<'a>(fixtures: &'a [Fixture], match_id: &str) -> &'a Fixture {
+        fixtures
+            .iter()
+            .find(|fixture| fixture.match_id == match_id)
+            .unwrap_or_else(|| panic!("no fixture {}", match_id))
+    }
+
+    #[test]
+    fn parse_schedule_reads_played_matches() {
+        let fixtures = parse_schedule(LEAGUE_PAGE, LINK_SELECTOR).unwrap();
+        let opener = fixture(&fixtures, "cc5b4244");
+        assert_eq!(opener.home_team_id, "19538871");
+        assert_eq!(opener.home_team_name, "Manchester Utd");
+        assert_eq!(opener.away_team_id, "fd962109");
+        assert_eq!(opener.away_team_name, "Fulham");
+        assert_eq!(
+            opener.match_date,
+            NaiveDate::from_ymd_opt(2024, 8, 16).unwrap()
+        );
+        assert_eq!((opener.home_score, opener.away_score), (Some(1), Some(0)));
+        assert_eq!(opener.notes, None);
+        assert_eq!(opener.status, FixtureStatus::Completed);
+    }
+
+    #[test]
+    fn parse_schedule_skips_head_to_head_spacer_and_header_rows() {
+        let fixtures = parse_schedule(LEAGUE_PAGE, LINK_SELECTOR).unwrap();
+        let match_ids: Vec<&str> = fixtures.iter().map(|f| f.match_id.as_str()).collect();
+        assert_eq!(match_ids, ["cc5b4244", "a1d0d529", "7c8f1a62"]);
+    }
+
+    #[test]
+    fn parse_schedule_marks_postponed_matches_from_notes() {
+        let fixtures = parse_schedule(LEAGUE_PAGE, LINK_SELECTOR).unwrap();
+        let postponed = fixture(&fixtures, "7c8f1a62");
+        assert_eq!((postponed.home_score, postponed.away_score), (None, None));
+        assert_eq!(postponed.notes.as_deref(), Some("Match Postponed"));
+        assert_eq!(postponed.status, FixtureStatus::Postponed);
+    }
+
+    #[test]
+    fn parse_schedule_reads_shootout_scores_without_penalties() {
+        let fixtures = parse_schedule(CUP_PAGE, LINK_SELECTOR).unwrap();
+        let semi_final = fixture(&fixtures, "5f9b0d2e");
+        assert_eq!(
+            (semi_final.home_score, semi_final.away_score),
+            (Some(3), Some(3))
+        );
+        assert_eq!(semi_final.status, FixtureStatus::Completed);
+        let final_match = fixture(&fixtures, "e2a4c7a0");
+        assert_eq!(
+            (final_match.home_score, final_match.away_score),
+            (Some(1), Some(2))
+        );
+    }
+
+    #[test]
+    fn parse_score_handles_dashes_and_shootouts() {
+        assert_eq!(parse_score("2–1"), Some((2, 1)));
+        assert_eq!(parse_score("0-0"), Some((0, 0)));
+        assert_eq!(parse_score("(4) 1–1 (3)"), Some((1, 1)));
+        assert_eq!(parse_score("(10) 2–2 (11)"), Some((2, 2)));
+        assert_eq!(parse_score(""), None);
+        assert_eq!(parse_score("Head-to-Head"), None);
+    }
+}
@@ -0,0 +1,262 @@
+use crate::config::Config;
+use crate::db::fbref::page::{
+    build_client, cell_link, cell_text, fetch_page, id_from_href, selector,
+};
+use crate::db::ingest::{get_match_info, persist_match_players, MatchInfo, PlayerMatch};
+use crate::db::pipeline::fetch_and_write;
+use crate::db::quarantine::{is_database_error, record_failure};
+use crate::db::request::{is_transient, is_unauthorized};
+use crate::db::runs::MatchCounts;
+use crate::db::scrape_status::{set_match_status, MatchStatus, ScrapeSource};
+use crate::db::stat_variables::{PlayerStat, Source, StatValue, STAT_VARIABLES};
+use postgres::Client as PgClient;
+use scraper::{ElementRef, Html, Selector};
+
+// The next match report is fetched while the last one is written
+pub fn get_match_stats(
+    db_client: &mut PgClient,
+    config: &Config,
+    counts: &mut MatchCounts,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let html_client = build_client()?;
+
+    let matches = get_match_info(db_client)?;
+
+    fetch_and_write(
+        matches,
+        |match_info| {
+            let url = format!(
+                "{}matches/{}",
+                config.fbref_ids.base_url, match_info.match_id
+            );
+            println!("Requesting match: {}", url);
+            fetch_page(&html_client, &url, config)
+        },
+        |match_info, page| {
+            counts.attempt();
+            let result = page
+                .map_err(|e| e.into())
+                .and_then(|page| store_match(db_client, &match_info, &page));
+            match result {
+                Ok(()) => {
+                    println!("Processed match: {}", match_info.match_id);
+                    counts.success();
+                }
+                Err(e) if is_transient(e.as_ref()) => {
+                    counts.source_unavailable();
+                    if !config.scraping_config.continue_on_error
+                        || is_unauthorized(e.as_ref())
+                        || counts.source_down()
+                    {
+                        return Err(format!(
+                            "Failed to request match {}: {}",
+                            match_info.match_id, e
+                        )
+                        .into());
+                    }
+                    println!("Skipping match {} after {}", match_info.match_id, e);
+                }
+                Err(e) if is_database_error(e.as_ref()) => return Err(e),
+                Err(e) => {
+                    println!("Quarantining match {} after {}", match_info.match_id, e);
+                    record_failure(db_client, &match_info.match_id, &e.to_string())?;
+                    counts.failure(&match_info, &e.to_string());
+                }
+            }
+            Ok(())
+        },
+    )
+}
+
+fn store_match(
+    db_client: &mut PgClient,
+    match_info: &MatchInfo,
+    page: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    set_match_status(
+        db_client,
+        &match_info.match_id,
+        MatchStatus::Fetched,
+        Some(ScrapeSource::Fbref),
+    )?;
+
+    let players = parse_match_report(page, match_info)?;
+    persist_match_players(db_client, match_info, &players, ScrapeSource::Fbref)
+}
+
+// Match reports carry one summary table per side with the id
+// stats_{team_id}_summary, one row per player who appeared.
+pub fn parse_match_report(
+    page: &str,
+    match_info: &MatchInfo,
+) -> Result<Vec<PlayerMatch>, Box<dyn std::error::Error>> {
+    let document = Html::parse_document(page);
+    let table_selector = selector("table[id^='stats_'][id$='_summary']")?;
+    let row_selector = selector("tbody tr")?;
+
+    let mut players = Vec::new();
+    let mut teams_seen = Vec::new();
+    for table in document.select(&table_selector) {
+        let table_id = table.value().attr("id").unwrap_or_default();
+        let team_id = table_id
+            .trim_start_matches("stats_")
+            .trim_end_matches("_summary")
+            .to_string();
+        if team_id != match_info.home_team_id && team_id != match_info.away_team_id {
+            return Err(format!("Team id '{}' not found in match", team_id).into());
+        }
+        teams_seen.push(team_id.clone());
+
+        for row in table.select(&row_selector) {
+            let (href, full_name) = match cell_link(&row, "player") {
+                Some(link) => link,
+                None => continue,
+            };
+            let player_id = id_from_href(&href, "/players/")
+                .ok_or(format!("Missing player id for {}", full_name))?;
+
+            players.push(PlayerMatch {
+                player_id,
+                full_name,
+                nationality: cell_text(&row, "nationality")
+                    .and_then(|text| text.split_whitespace().last().map(|s| s.to_string())),
+                team_id: team_id.clone(),
+                stats: row_stats(&row),
+            });
+        }
+    }
+
+    if teams_seen.len() != 2 {
+        return Err(format!("Expected 2 summary tables, found {}", teams_seen.len()).into());
+    }
+    Ok(players)
+}
+
+fn row_stats(row: &ElementRef) -> Vec<PlayerStat> {
+    STAT_VARIABLES
+        .iter()
+        .filter_map(|stat| {
+            let value = match stat.fbref_source {
+                Source::Cell(data_stat) => {
+                    cell_text(row, data_stat).and_then(|raw| stat.parse(&raw))
+                }
+                Source::StartingEleven => Some(StatValue::flag(!is_substitute(row))),
+                Source::MetaData(_) | Source::Summary(_) | Source::Unreported => None,
+            };
+            stat.with_default(value)
+        })
+        .collect()
+}
+
+// Substitutes are indented under the player they replaced with leading
+// non-breaking spaces in the player cell
+fn is_substitute(row: &ElementRef) -> bool {
+    Selector::parse("[data-stat='player']")
+        .ok()
+        .and_then(|player_selector| row.select(&player_selector).next())
+        .and_then(|cell| cell.text().next())
+        .is_some_and(|text| text.starts_with('\u{a0}'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MATCH_REPORT: &str = include_str!("../../../tests/fixtures/fbref/match_report.html");
+
+    fn match_info() -> MatchInfo {
+        MatchInfo {
+            match_id: "3a6836b4".to_string(),
+            league_id: "9".to_string(),
+            season_id: "2023-2024".to_string(),
+            home_team_id: "943e8050".to_string(),
+            home_team_name: "Burnley".to_string(),
+            away_team_id: "b8fd03ef".to_string(),
+            away_team_name: "Manchester City".to_string(),
+        }
+    }
+
+    fn player<'a>(players: &'a [PlayerMatch], player_id: &str) -> &'a PlayerMatch {
+        players
+            .iter()
+            .find(|player| player.player_id == player_id)
+            .unwrap_or_else(|| panic!("no player {}", player_id))
+    }
+
+    fn number(player: &PlayerMatch, variable: &str) -> Option<f64> {
+        player
+            .stats
+            .iter()
+            .find(|stat| stat.variable == variable)
+            .and_then(|stat| match stat.value {
+                StatValue::Number(number) => Some(number),
+                StatValue::Text(_) => None,
+            })
+    }
+
+    fn text<'a>(player: &'a PlayerMatch, variable: &str) -> Option<&'a str> {
+        player
+            .stats
+            .iter()
+            .find(|stat| stat.variable == variable)
+            .and_then(|stat| match &stat.value {
+                StatValue::Text(text) => Some(text.as_str()),
+                StatValue::Number(_) => None,
+            })
+    }
+
+    #[test]
+    fn parse_match_report_reads_both_sides() {
+        let players = parse_match_report(MATCH_REPORT, &match_info()).unwrap();
+        assert_eq!(players.len(), 8);
+        assert_eq!(
+            players.iter().filter(|p| p.team_id == "943e8050").count(),
+            4
+        );
+        assert_eq!(
+            players.iter().filter(|p| p.team_id == "b8fd03ef").count(),
+            4
+        );
+    }
+
+    #[test]
+    fn parse_match_report_reads_player_stats() {
+        let players = parse_match_report(MATCH_REPORT, &match_info()).unwrap();
+        let haaland = player(&players, "1f44ac21");
+        assert_eq!(haaland.full_name, "Erling Haaland");
+        assert_eq!(haaland.nationality.as_deref(), Some("NOR"));
+        assert_eq!(number(haaland, "mins_played"), Some(90.0));
+        assert_eq!(number(haaland, "goals"), Some(2.0));
+        assert_eq!(number(haaland, "shots_on_target"), Some(3.0));
+        assert_eq!(number(haaland, "xg"), Some(1.4));
+        assert_eq!(text(haaland, "position"), Some("FW"));
+    }
+
+    #[test]
+    fn parse_match_report_flags_indented_substitutes() {
+        let players = parse_match_report(MATCH_REPORT, &match_info()).unwrap();
+        let starter = player(&players, "0ad5d9b0");
+        assert_eq!(number(starter, "started"), Some(1.0));
+        let substitute = player(&players, "b66315ae");
+        assert_eq!(substitute.full_name, "Julián Álvarez");
+        assert_eq!(number(substitute, "started"), Some(0.0));
+        assert_eq!(number(substitute, "mins_played"), Some(25.0));
+        assert_eq!(number(substitute, "assists"), Some(1.0));
+    }
+
+    #[test]
+    fn parse_match_report_skips_totals_row() {
+        let players = parse_match_report(MATCH_REPORT, &match_info()).unwrap();
+        assert!(!players.iter().any(|p| p.full_name.ends_with("Players")));
+    }
+
+    #[test]
+    fn parse_match_report_rejects_unknown_team() {
+        let mut match_info = match_info();
+        match_info.away_team_id = "18bb7c10".to_string();
+        match parse_match_report(MATCH_REPORT, &match_info) {
+            Err(e) => assert_eq!(e.to_string(), "Team id 'b8fd03ef' not found in match"),
+            Ok(_) => panic!("expected an unknown team error"),
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod fixtures;
+pub mod matches;
+pub mod page;
+pub mod seasons;
@@ -0,0 +1,54 @@
+use crate::config::Config;
+use crate::db::rate_limit::RateLimiter;
+use crate::db::request::{send_text, RequestError};
+use reqwest::blocking::Client as HTMLClient;
+use scraper::{ElementRef, Selector};
+
+// FBref rejects requests without a browser-like user agent
+const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) cnxns/0.1";
+
+pub fn build_client() -> Result<HTMLClient, Box<dyn std::error::Error>> {
+    Ok(HTMLClient::builder().user_agent(USER_AGENT).build()?)
+}
+
+pub fn fetch_page(client: &HTMLClient, url: &str, config: &Config) -> Result<String, RequestError> {
+    send_text(
+        client.get(url),
+        RateLimiter::for_config(config).fbref(),
+        config.scraping_config.max_retries,
+    )
+}
+
+pub fn selector(css: &str) -> Result<Selector, Box<dyn std::error::Error>> {
+    Selector::parse(css).map_err(|e| format!("Invalid selector '{}': {}", css, e).into())
+}
+
+// Pulls the id out of FBref links such as /en/squads/822bd0ba/Liverpool-Stats
+pub fn id_from_href(href: &str, segment: &str) -> Option<String> {
+    let rest = href.split(segment).nth(1)?;
+    let id = rest.split('/').next()?;
+    if id.is_empty() {
+        None
+    } else {
+        Some(id.to_string())
+    }
+}
+
+pub fn cell_text(row: &ElementRef, stat: &str) -> Option<String> {
+    let cell_selector = Selector::parse(&format!("[data-stat='{}']", stat)).ok()?;
+    let text: String = row.select(&cell_selector).next()?.text().collect();
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+pub fn cell_link(row: &ElementRef, stat: &str) -> Option<(String, String)> {
+    let link_selector = Selector::parse(&format!("[data-stat='{}'] a", stat)).ok()?;
+    let link = row.select(&link_selector).next()?;
+    let href = link.value().attr("href")?.to_string();
+    let text: String = link.text().collect();
+    Some((href, text.trim().to_string()))
+}
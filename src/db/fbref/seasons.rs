@@ -0,0 +1,100 @@
+use crate::config::{Config, LeagueConfig};
+use crate::db::fbref::page::{cell_text, fetch_page, selector};
+use crate::db::ingest::{persist_competition_seasons, stored_competition_seasons};
+use postgres::Client as PgClient;
+use reqwest::blocking::Client as HTMLClient;
+use scraper::Html;
+
+// The seasons to scrape for a league. Ranges and latest-N read the
+// competition history page, falling back to the last list seen if that page
+// fails after its retries.
+pub fn planned_seasons(
+    db_client: &mut PgClient,
+    html_client: &HTMLClient,
+    config: &Config,
+    league: &LeagueConfig,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if !league.season_ids.needs_discovery() {
+        return Ok(config.league_seasons(league, &[]));
+    }
+
+    let league_id = league.league_id.to_string();
+    let url = history_url(&config.fbref_ids.base_url, &league_id, &league.league_name);
+    println!("Requesting: {}", url);
+    let available = match fetch_page(html_client, &url, config) {
+        Ok(page) => {
+            let season_ids = parse_history(&page)?;
+            persist_competition_seasons(db_client, &league_id, &season_ids)?;
+            season_ids
+        }
+        Err(e) => {
+            let stored = stored_competition_seasons(db_client, &league_id)?;
+            if stored.is_empty() {
+                return Err(format!("No seasons known for {}: {}", league.league_name, e).into());
+            }
+            println!(
+                "Using stored seasons for {} after {}",
+                league.league_name, e
+            );
+            stored
+        }
+    };
+
+    Ok(config.league_seasons(league, &available))
+}
+
+// e.g. https://fbref.com/en/comps/9/history/Premier-League-Seasons
+pub fn history_url(base_url: &str, league_id: &str, league_name: &str) -> String {
+    format!(
+        "{}comps/{}/history/{}-Seasons",
+        base_url, league_id, league_name
+    )
+}
+
+// The history page lists one row per season, newest first, with the header
+// row repeated every so often inside the body
+pub fn parse_history(page: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let document = Html::parse_document(page);
+    let row_selector = selector("table#seasons tbody tr:not(.thead)")?;
+
+    Ok(document
+        .select(&row_selector)
+        .filter_map(|row| cell_text(&row, "year_id"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HISTORY_PAGE: &str =
+        include_str!("../../../tests/fixtures/fbref/history_premier_league.html");
+
+    #[test]
+    fn parse_history_lists_seasons_newest_first() {
+        let season_ids = parse_history(HISTORY_PAGE).unwrap();
+        assert_eq!(
+            season_ids,
+            [
+                "2024-2025",
+                "2023-2024",
+                "2022-2023",
+                "1993-1994",
+                "1992-1993"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_history_skips_repeated_header_rows() {
+        let season_ids = parse_history(HISTORY_PAGE).unwrap();
+        assert!(!season_ids.iter().any(|season_id| season_id == "Season"));
+    }
+
+    #[test]
+    fn parse_history_without_seasons_table_is_empty() {
+        assert!(parse_history("<html><body></body></html>")
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+use crate::db::ingest::MatchInfo;
+
+// Narrows a scraper command to one league, one season or the first few
+// items of its backlog, so a single season can be pulled or a new league
+// tried without running through everything else
+#[derive(Default)]
+pub struct ScrapeFilter {
+    pub league_id: Option<String>,
+    pub season_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl ScrapeFilter {
+    pub fn includes_league(&self, league_id: &str) -> bool {
+        self.league_id.as_deref().is_none_or(|id| id == league_id)
+    }
+
+    pub fn includes(&self, league_id: &str, season_id: &str) -> bool {
+        self.includes_league(league_id)
+            && self.season_id.as_deref().is_none_or(|id| id == season_id)
+    }
+
+    pub fn under_limit(&self, taken: usize) -> bool {
+        self.limit.is_none_or(|limit| taken < limit)
+    }
+
+    pub fn select_matches(&self, matches: Vec<MatchInfo>) -> Vec<MatchInfo> {
+        matches
+            .into_iter()
+            .filter(|match_info| self.includes(&match_info.league_id, &match_info.season_id))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
@@ -0,0 +1,469 @@
+use crate::db::psql::ingest::{INSERT_APPEARANCES, INSERT_PLAYERS, INSERT_PLAYER_STATS};
+use crate::db::psql::players::DERIVE_PLAYER_CLUBS;
+use crate::db::psql::refresh::{GET_SEASON_FIXTURES, UPDATE_REFRESHED_SEASON, UPSERT_FIXTURE};
+use crate::db::psql::repair::{
+    CLEAR_MATCH_APPEARANCES, CLEAR_MATCH_STATS, FIND_PARTIAL_MATCHES, RESET_MATCH_STATUS,
+};
+use crate::db::psql::seasons::{LIST_COMPETITION_SEASONS, UPSERT_COMPETITION_SEASON};
+use crate::db::psql::update_scrape_status::GET_LEAGUE_SEASON_STATUS;
+use crate::db::scrape_status::{set_match_status, FixtureStatus, MatchStatus, ScrapeSource};
+use crate::db::stat_variables::{PlayerStat, StatValue, REPAIR_VARIABLES};
+use chrono::NaiveDate;
+use postgres::{Client as PgClient, GenericClient};
+use std::collections::HashMap;
+
+pub struct Fixture {
+    pub match_id: String,
+    pub home_team_id: String,
+    pub home_team_name: String,
+    pub away_team_id: String,
+    pub away_team_name: String,
+    pub match_date: NaiveDate,
+    pub home_score: Option<i32>,
+    pub away_score: Option<i32>,
+    pub notes: Option<String>,
+    pub status: FixtureStatus,
+}
+
+// What a refresh changed for one league season
+#[derive(Default)]
+pub struct RefreshSummary {
+    pub added: usize,
+    pub rescheduled: usize,
+    pub newly_played: usize,
+}
+
+pub struct MatchInfo {
+    pub match_id: String,
+    pub league_id: String,
+    pub season_id: String,
+    pub home_team_id: String,
+    pub home_team_name: String,
+    pub away_team_id: String,
+    pub away_team_name: String,
+}
+
+pub struct PlayerMatch {
+    pub player_id: String,
+    pub full_name: String,
+    pub nationality: Option<String>,
+    pub team_id: String,
+    pub stats: Vec<PlayerStat>,
+}
+
+impl MatchInfo {
+    pub fn team_id_for(&self, team_name: &str) -> Result<&str, Box<dyn std::error::Error>> {
+        if team_name == self.home_team_name {
+            Ok(&self.home_team_id)
+        } else if team_name == self.away_team_name {
+            Ok(&self.away_team_id)
+        } else {
+            Err(format!("Team name '{}' not found in match", team_name).into())
+        }
+    }
+}
+
+pub fn persist_league_fixtures(
+    db_client: &mut PgClient,
+    league_id: &str,
+    league_name: &str,
+    season_id: &str,
+    fixtures: &[Fixture],
+) -> Result<(), Box<dyn std::error::Error>> {
+    db_client.execute(
+        "INSERT INTO league_seasons (league_id, league_name, season_id, number_matches, ongoing)
+         VALUES ($1, $2, $3, $4, $5) ON CONFLICT (league_id, season_id) DO NOTHING",
+        &[
+            &league_id,
+            &league_name,
+            &season_id,
+            &(fixtures.len() as i32),
+            &is_ongoing(fixtures),
+        ],
+    )?;
+
+    for fixture in fixtures {
+        if let Err(e) = upsert_fixture(db_client, league_id, season_id, fixture) {
+            println!("Failed to insert match {}: {}", fixture.match_id, e);
+            return Err(e);
+        }
+    }
+
+    println!(
+        "Saved {} matches for {} {}",
+        fixtures.len(),
+        league_name,
+        season_id
+    );
+    Ok(())
+}
+
+// Re-applies the latest fixture list for a season that is already stored:
+// new fixtures are added, rescheduled ones take their new date, and fixtures
+// reported completed are queued for stats. The season stays ongoing while
+// any fixture is still to be played.
+pub fn refresh_league_fixtures(
+    db_client: &mut PgClient,
+    league_id: &str,
+    season_id: &str,
+    fixtures: &[Fixture],
+) -> Result<RefreshSummary, Box<dyn std::error::Error>> {
+    let mut transaction = db_client.transaction()?;
+
+    let known: HashMap<String, (Option<NaiveDate>, String)> = transaction
+        .query(GET_SEASON_FIXTURES, &[&league_id, &season_id])?
+        .iter()
+        .map(|row| {
+            (
+                row.get("match_id"),
+                (row.get("match_date"), row.get("scrape_status")),
+            )
+        })
+        .collect();
+
+    let mut summary = RefreshSummary::default();
+    for fixture in fixtures {
+        match known.get(&fixture.match_id) {
+            None => summary.added += 1,
+            Some((match_date, status)) => {
+                if *match_date != Some(fixture.match_date) {
+                    summary.rescheduled += 1;
+                }
+                if status == MatchStatus::NotYetPlayed.as_str()
+                    && fixture.status == FixtureStatus::Completed
+                {
+                    summary.newly_played += 1;
+                }
+            }
+        }
+        upsert_fixture(&mut transaction, league_id, season_id, fixture)?;
+    }
+
+    let number_matches = known
+        .keys()
+        .filter(|match_id| !fixtures.iter().any(|f| &f.match_id == *match_id))
+        .count()
+        + fixtures.len();
+    transaction.execute(
+        UPDATE_REFRESHED_SEASON,
+        &[
+            &league_id,
+            &season_id,
+            &(number_matches as i32),
+            &is_ongoing(fixtures),
+        ],
+    )?;
+    transaction.commit()?;
+    Ok(summary)
+}
+
+fn upsert_fixture(
+    db_client: &mut impl GenericClient,
+    league_id: &str,
+    season_id: &str,
+    fixture: &Fixture,
+) -> Result<(), Box<dyn std::error::Error>> {
+    db_client.execute(
+        UPSERT_FIXTURE,
+        &[
+            &league_id,
+            &season_id,
+            &fixture.match_id,
+            &fixture.home_team_id,
+            &fixture.home_team_name,
+            &fixture.away_team_id,
+            &fixture.away_team_name,
+            &fixture.match_date,
+            &fixture.status.as_str(),
+            &fixture.home_score,
+            &fixture.away_score,
+            &fixture.notes,
+            &fixture.status.scrape_status().as_str(),
+        ],
+    )?;
+    Ok(())
+}
+
+fn is_ongoing(fixtures: &[Fixture]) -> bool {
+    fixtures.iter().any(|fixture| !fixture.status.is_played())
+}
+
+// Records the seasons a data source lists for a league so a later run can
+// still plan if the source is unreachable
+pub fn persist_competition_seasons(
+    db_client: &mut PgClient,
+    league_id: &str,
+    season_ids: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut transaction = db_client.transaction()?;
+    for season_id in season_ids {
+        transaction.execute(UPSERT_COMPETITION_SEASON, &[&league_id, season_id])?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+pub fn stored_competition_seasons(
+    db_client: &mut PgClient,
+    league_id: &str,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let rows = db_client.query(LIST_COMPETITION_SEASONS, &[&league_id])?;
+    Ok(rows.iter().map(|row| row.get("season_id")).collect())
+}
+
+// A league season is complete once every known match is ingested or skipped
+pub fn check_league_status(
+    db_client: &mut PgClient,
+    league_id: String,
+    season_id: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let rows = db_client.query(GET_LEAGUE_SEASON_STATUS, &[&league_id, &season_id])?;
+
+    if let Some(row) = rows.first() {
+        let known: i64 = row.get("matches_known");
+        let ingested: i64 = row.get("matches_ingested");
+        let skipped: i64 = row.get("matches_skipped");
+        return Ok(known > 0 && known == ingested + skipped);
+    }
+
+    Ok(false)
+}
+
+pub fn get_match_info(
+    db_client: &mut PgClient,
+) -> Result<Vec<MatchInfo>, Box<dyn std::error::Error>> {
+    let rows: Vec<postgres::Row> = db_client.query(
+        "SELECT match_id, home_team_id, home_team_name, away_team_id, away_team_name, league_id, season_id
+        FROM matches
+        WHERE scrape_status IN ('pending', 'fetched')
+          AND fixture_status = 'completed'
+        ORDER BY league_id, season_id, match_date, match_id",
+        &[],
+    )?;
+
+    Ok(rows.iter().map(match_info_from_row).collect())
+}
+
+pub fn get_match_info_by_id(
+    db_client: &mut PgClient,
+    match_id: &str,
+) -> Result<MatchInfo, Box<dyn std::error::Error>> {
+    let row = db_client
+        .query_opt(
+            "SELECT match_id, home_team_id, home_team_name, away_team_id, away_team_name, league_id, season_id
+            FROM matches
+            WHERE match_id = $1",
+            &[&match_id],
+        )?
+        .ok_or(format!("Match {} not found in matches table", match_id))?;
+
+    Ok(match_info_from_row(&row))
+}
+
+fn match_info_from_row(row: &postgres::Row) -> MatchInfo {
+    MatchInfo {
+        match_id: row.get(0),
+        home_team_id: row.get(1),
+        home_team_name: row.get(2),
+        away_team_id: row.get(3),
+        away_team_name: row.get(4),
+        league_id: row.get(5),
+        season_id: row.get(6),
+    }
+}
+
+pub fn persist_match_players(
+    db_client: &mut PgClient,
+    match_info: &MatchInfo,
+    players: &[PlayerMatch],
+    source: ScrapeSource,
+) -> Result<(), Box<dyn std::error::Error>> {
+    write_match_players(db_client, match_info, players, source, false)
+}
+
+// Used when rebuilding from archived responses: the match's existing stats
+// are swapped for the freshly parsed ones in the same transaction
+pub fn replace_match_players(
+    db_client: &mut PgClient,
+    match_info: &MatchInfo,
+    players: &[PlayerMatch],
+    source: ScrapeSource,
+) -> Result<(), Box<dyn std::error::Error>> {
+    write_match_players(db_client, match_info, players, source, true)
+}
+
+fn write_match_players(
+    db_client: &mut PgClient,
+    match_info: &MatchInfo,
+    players: &[PlayerMatch],
+    source: ScrapeSource,
+    replace: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for team_id in [&match_info.home_team_id, &match_info.away_team_id] {
+        if !players.iter().any(|player| &player.team_id == team_id) {
+            return Err(format!("No players found for team {}", team_id).into());
+        }
+    }
+
+    // A match is written whole or not at all, together with its move to
+    // ingested, so a failure part way through leaves it fetched and with no
+    // player_stats rows, ready to be picked up again next run
+    let mut transaction = db_client.transaction()?;
+    if replace {
+        let match_ids = vec![match_info.match_id.clone()];
+        transaction.execute(CLEAR_MATCH_STATS, &[&match_ids])?;
+        transaction.execute(CLEAR_MATCH_APPEARANCES, &[&match_ids])?;
+    }
+    persist_match_rows(&mut transaction, match_info, players)?;
+    set_match_status(
+        &mut transaction,
+        &match_info.match_id,
+        MatchStatus::Ingested,
+        Some(source),
+    )?;
+    transaction.commit()?;
+    Ok(())
+}
+
+// Matches written by runs before ingestion was transactional can be missing
+// a team or some of a player's variables. Their stats are cleared so the
+// match is fetched again.
+pub fn repair_partial_matches(
+    db_client: &mut PgClient,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut transaction = db_client.transaction()?;
+    let match_ids: Vec<String> = transaction
+        .query(FIND_PARTIAL_MATCHES, &[&REPAIR_VARIABLES])?
+        .iter()
+        .map(|row| row.get("match_id"))
+        .collect();
+
+    if !match_ids.is_empty() {
+        transaction.execute(CLEAR_MATCH_STATS, &[&match_ids])?;
+        transaction.execute(CLEAR_MATCH_APPEARANCES, &[&match_ids])?;
+        transaction.execute(RESET_MATCH_STATUS, &[&match_ids])?;
+    }
+    transaction.commit()?;
+    Ok(match_ids)
+}
+
+// Recomputes current_club and active from each player's latest appearance
+pub fn derive_player_clubs(db_client: &mut PgClient) -> Result<u64, Box<dyn std::error::Error>> {
+    Ok(db_client.execute(DERIVE_PLAYER_CLUBS, &[])?)
+}
+
+// Players, stats and appearances for a match go in as one multi-row insert
+// each, which matters most over the network to the Pi
+fn persist_match_rows(
+    db_client: &mut impl GenericClient,
+    match_info: &MatchInfo,
+    players: &[PlayerMatch],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut player_ids = Vec::new();
+    let mut full_names = Vec::new();
+    let mut nationalities = Vec::new();
+    let mut stats = StatRows::default();
+    let mut appearances = AppearanceRows::default();
+    for player in players {
+        player_ids.push(player.player_id.as_str());
+        full_names.push(player.full_name.as_str());
+        nationalities.push(player.nationality.as_deref());
+        stats.push(player);
+        appearances.push(player);
+    }
+
+    db_client.execute(INSERT_PLAYERS, &[&player_ids, &full_names, &nationalities])?;
+    db_client.execute(
+        INSERT_PLAYER_STATS,
+        &[
+            &match_info.match_id,
+            &stats.team_ids,
+            &stats.player_ids,
+            &stats.variables,
+            &stats.values,
+            &stats.value_texts,
+        ],
+    )?;
+    db_client.execute(
+        INSERT_APPEARANCES,
+        &[
+            &match_info.match_id,
+            &appearances.team_ids,
+            &appearances.player_ids,
+            &appearances.minutes,
+            &appearances.started,
+            &appearances.goals,
+            &appearances.assists,
+            &appearances.positions,
+        ],
+    )?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct StatRows<'a> {
+    team_ids: Vec<&'a str>,
+    player_ids: Vec<&'a str>,
+    variables: Vec<&'a str>,
+    values: Vec<Option<f64>>,
+    value_texts: Vec<Option<&'a str>>,
+}
+
+impl<'a> StatRows<'a> {
+    fn push(&mut self, player: &'a PlayerMatch) {
+        for stat in &player.stats {
+            let (value, value_text) = match &stat.value {
+                StatValue::Number(number) => (Some(*number), None),
+                StatValue::Text(text) => (None, Some(text.as_str())),
+            };
+            self.team_ids.push(&player.team_id);
+            self.player_ids.push(&player.player_id);
+            self.variables.push(stat.variable);
+            self.values.push(value);
+            self.value_texts.push(value_text);
+        }
+    }
+}
+
+// The appearance row carries the variables the game reads, typed, next to
+// the full set in player_stats
+#[derive(Default)]
+struct AppearanceRows<'a> {
+    team_ids: Vec<&'a str>,
+    player_ids: Vec<&'a str>,
+    minutes: Vec<i32>,
+    started: Vec<Option<bool>>,
+    goals: Vec<i32>,
+    assists: Vec<i32>,
+    positions: Vec<Option<&'a str>>,
+}
+
+impl<'a> AppearanceRows<'a> {
+    fn push(&mut self, player: &'a PlayerMatch) {
+        let value = |variable: &str| {
+            player
+                .stats
+                .iter()
+                .find(|stat| stat.variable == variable)
+                .map(|stat| &stat.value)
+        };
+        let number = |variable: &str| match value(variable) {
+            Some(StatValue::Number(number)) => Some(*number),
+            _ => None,
+        };
+
+        self.team_ids.push(&player.team_id);
+        self.player_ids.push(&player.player_id);
+        self.minutes
+            .push(number("mins_played").unwrap_or_default() as i32);
+        self.started
+            .push(number("started").map(|started| started > 0.0));
+        self.goals.push(number("goals").unwrap_or_default() as i32);
+        self.assists
+            .push(number("assists").unwrap_or_default() as i32);
+        self.positions.push(match value("position") {
+            Some(StatValue::Text(position)) => Some(position.as_str()),
+            _ => None,
+        });
+    }
+}
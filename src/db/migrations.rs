@@ -0,0 +1,292 @@
+use crate::app::psql::app_schema::{CREATE_DAILY_SELECTION_TABLE, CREATE_TEAMS_TABLE};
+use crate::db::psql::migrations::{
+    CLEAR_FBRAPI_STARTED, CREATE_APPEARANCES_TABLE, CREATE_MIGRATIONS_TABLE, CREATE_READONLY_ROLE,
+    CREATE_SCRAPE_RUNS_TABLE, GET_SCHEMA_VERSION, LIST_APPLIED_MIGRATIONS, MIGRATIONS_TABLE_EXISTS,
+    RECORD_MIGRATION, WIDEN_RUN_COMMAND,
+};
+use crate::db::psql::schema::CREATE_TABLES_SQL;
+use chrono::NaiveDateTime;
+use postgres::Client as PgClient;
+use sha2::{Digest, Sha256};
+
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.sql))
+    }
+}
+
+// Applied in order by `migrate up` and never edited once released: a
+// schema change is a new migration at the end of the list. The first three
+// are the IF NOT EXISTS scripts the binaries used to run on start, so they
+// also adopt databases created before migrations were tracked.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "scraper_tables",
+        sql: CREATE_TABLES_SQL,
+    },
+    Migration {
+        version: 2,
+        name: "teams",
+        sql: CREATE_TEAMS_TABLE,
+    },
+    Migration {
+        version: 3,
+        name: "daily_selection",
+        sql: CREATE_DAILY_SELECTION_TABLE,
+    },
+    Migration {
+        version: 4,
+        name: "readonly_role",
+        sql: CREATE_READONLY_ROLE,
+    },
+    Migration {
+        version: 5,
+        name: "appearances",
+        sql: CREATE_APPEARANCES_TABLE,
+    },
+    Migration {
+        version: 6,
+        name: "scrape_runs",
+        sql: CREATE_SCRAPE_RUNS_TABLE,
+    },
+    Migration {
+        version: 7,
+        name: "clear_fbrapi_started",
+        sql: CLEAR_FBRAPI_STARTED,
+    },
+    Migration {
+        version: 8,
+        name: "widen_run_command",
+        sql: WIDEN_RUN_COMMAND,
+    },
+];
+
+pub struct AppliedMigration {
+    pub version: i32,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at_utc: Option<NaiveDateTime>,
+}
+
+// The schema version this build expects
+pub fn latest_version() -> i32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+// Shared by every binary that reads or writes the schema, so none of them
+// runs against tables it does not know about
+pub fn check_version(version: i32) -> Result<(), String> {
+    if version < latest_version() {
+        Err(format!(
+            "Database schema is at version {} but this build expects {}, run `migrate up` first",
+            version,
+            latest_version()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn schema_version(db_client: &mut PgClient) -> Result<i32, Box<dyn std::error::Error>> {
+    let tracked: bool = db_client
+        .query_one(MIGRATIONS_TABLE_EXISTS, &[])?
+        .get("tracked");
+    if !tracked {
+        return Ok(0);
+    }
+    Ok(db_client.query_one(GET_SCHEMA_VERSION, &[])?.get("version"))
+}
+
+pub fn require_current_schema(db_client: &mut PgClient) -> Result<(), Box<dyn std::error::Error>> {
+    check_version(schema_version(db_client)?)?;
+    Ok(())
+}
+
+fn applied_migrations(
+    db_client: &mut PgClient,
+) -> Result<Vec<AppliedMigration>, Box<dyn std::error::Error>> {
+    let tracked: bool = db_client
+        .query_one(MIGRATIONS_TABLE_EXISTS, &[])?
+        .get("tracked");
+    if !tracked {
+        return Ok(Vec::new());
+    }
+
+    let rows = db_client.query(LIST_APPLIED_MIGRATIONS, &[])?;
+    Ok(rows
+        .iter()
+        .map(|row| AppliedMigration {
+            version: row.get("version"),
+            name: row.get("name"),
+            checksum: row.get("checksum"),
+            applied_at_utc: row.get("applied_at_utc"),
+        })
+        .collect())
+}
+
+// An applied migration whose SQL has since changed, or one missing from
+// below the current version, means the database and this build disagree on
+// history. Neither is repaired automatically.
+fn check_history(applied: &[AppliedMigration]) -> Result<(), Box<dyn std::error::Error>> {
+    let current = applied.iter().map(|m| m.version).max().unwrap_or(0);
+    for migration in MIGRATIONS {
+        match applied.iter().find(|m| m.version == migration.version) {
+            Some(recorded) if recorded.checksum != migration.checksum() => {
+                return Err(format!(
+                    "Migration {} {} has changed since it was applied",
+                    migration.version, migration.name
+                )
+                .into());
+            }
+            None if migration.version < current => {
+                return Err(format!(
+                    "Migration {} {} was never applied but the schema is at version {}",
+                    migration.version, migration.name, current
+                )
+                .into());
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Applies each pending migration in its own transaction, so a failure
+// leaves the schema at the last migration that succeeded. Migrations create
+// their tables unqualified, in whichever schema the connection's
+// search_path names.
+pub fn migrate_up(
+    db_client: &mut PgClient,
+    schema: &str,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    db_client.batch_execute(&format!("CREATE SCHEMA IF NOT EXISTS {}", schema))?;
+    db_client.batch_execute(CREATE_MIGRATIONS_TABLE)?;
+    let applied = applied_migrations(db_client)?;
+    check_history(&applied)?;
+
+    let current = applied.iter().map(|m| m.version).max().unwrap_or(0);
+    let pending: Vec<&Migration> = MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > current)
+        .collect();
+
+    for migration in &pending {
+        let mut transaction = db_client.transaction()?;
+        transaction.batch_execute(migration.sql)?;
+        transaction.execute(
+            RECORD_MIGRATION,
+            &[&migration.version, &migration.name, &migration.checksum()],
+        )?;
+        transaction.commit()?;
+        println!("Applied migration {} {}", migration.version, migration.name);
+    }
+    Ok(pending.len())
+}
+
+// Backs `migrate status`
+pub fn print_status(db_client: &mut PgClient) -> Result<(), Box<dyn std::error::Error>> {
+    let applied = applied_migrations(db_client)?;
+
+    for migration in MIGRATIONS {
+        let state = match applied.iter().find(|m| m.version == migration.version) {
+            Some(recorded) if recorded.checksum != migration.checksum() => {
+                "applied, checksum mismatch".to_string()
+            }
+            Some(recorded) => match recorded.applied_at_utc {
+                Some(applied_at) => format!("applied {}", applied_at.format("%Y-%m-%d %H:%M")),
+                None => "applied".to_string(),
+            },
+            None => "pending".to_string(),
+        };
+        println!("{:>4} {:<24} {}", migration.version, migration.name, state);
+    }
+    for recorded in applied.iter().filter(|m| m.version > latest_version()) {
+        println!(
+            "{:>4} {:<24} applied, not in this build",
+            recorded.version, recorded.name
+        );
+    }
+
+    let version = applied.iter().map(|m| m.version).max().unwrap_or(0);
+    println!(
+        "Schema is at version {}, this build expects {}",
+        version,
+        latest_version()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Migrations are frozen from here on. A changed checksum means an
+    // applied migration was edited; the change belongs in a new one.
+    const FROZEN: &[(i32, &str)] = &[
+        (
+            1,
+            "97273bb74535937a386ae1335dabaf34809fce56d075e10b1ec56a62f7c2b528",
+        ),
+        (
+            2,
+            "d7dd438adc16c8864a80fa63709440c6c5d41a0469ed7e417a8994e8c97b989f",
+        ),
+        (
+            3,
+            "e59ed99d14a7d004240631fd9697ed75ce1a17ec300f654a96a4f0ae09400722",
+        ),
+        (
+            4,
+            "fbfe563a4491e931dbfcc3d510b4b8afa7d2faeafbe7c7c35f382236e9d9c897",
+        ),
+        (
+            5,
+            "37a3be9e81dc76e537376945197d7ceab07d2598ff66a26b868823f73e079b02",
+        ),
+        (
+            6,
+            "2a8155daaa55d8633e0488b5a221b49f7bea4030b53b3341c5faa9d8feb0bab3",
+        ),
+        (
+            7,
+            "6c086a3bb734ef0767661854feddd2be2c5e89e3b6d2357099787310c9efbf0d",
+        ),
+        (
+            8,
+            "09cee38bffd1dcf2f92ecfea81630e0ef3992270f8b1ce80167e88ea1a4f300c",
+        ),
+    ];
+
+    #[test]
+    fn applied_migrations_are_not_edited() {
+        for (version, checksum) in FROZEN {
+            let migration = MIGRATIONS.iter().find(|m| m.version == *version).unwrap();
+            assert_eq!(migration.checksum(), *checksum, "migration {}", version);
+        }
+    }
+
+    #[test]
+    fn migrations_name_no_schema() {
+        for migration in MIGRATIONS {
+            assert!(
+                !migration.sql.contains("connections."),
+                "migration {}",
+                migration.version
+            );
+        }
+    }
+
+    #[test]
+    fn versions_are_consecutive() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as i32 + 1);
+        }
+    }
+}
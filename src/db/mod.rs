@@ -1,2 +1,15 @@
 pub mod api;
+pub mod archive;
+pub mod connection;
+pub mod fbref;
+pub mod filter;
+pub mod ingest;
+pub mod migrations;
+pub mod pipeline;
 pub mod psql;
+pub mod quarantine;
+pub mod rate_limit;
+pub mod request;
+pub mod runs;
+pub mod scrape_status;
+pub mod stat_variables;
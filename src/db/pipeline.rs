@@ -0,0 +1,36 @@
+use std::sync::mpsc;
+use std::thread;
+
+// Fetches each item on a background thread while the caller writes the
+// previous response, so the network wait overlaps with the database work.
+// The channel has no buffer: the fetcher holds at most one response the
+// writer has not taken yet. When `write` returns an error the receiver is
+// dropped and the fetcher stops after its current request.
+pub fn fetch_and_write<T, R, F, W>(
+    items: Vec<T>,
+    fetch: F,
+    mut write: W,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    T: Send,
+    R: Send,
+    F: Fn(&T) -> R + Send,
+    W: FnMut(T, R) -> Result<(), Box<dyn std::error::Error>>,
+{
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::sync_channel(0);
+        scope.spawn(move || {
+            for item in items {
+                let response = fetch(&item);
+                if sender.send((item, response)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        for (item, response) in receiver {
+            write(item, response)?;
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,21 @@
+pub const ARCHIVE_RESPONSE: &str = r#"
+INSERT INTO api_responses (endpoint, params, response)
+VALUES ($1, $2, $3)
+ON CONFLICT (endpoint, params) DO UPDATE SET
+    response = EXCLUDED.response,
+    fetched_at_utc = NOW()
+"#;
+
+pub const LIST_ARCHIVED_PARAMS: &str = r#"
+SELECT params
+FROM api_responses
+WHERE endpoint = $1
+ORDER BY fetched_at_utc
+"#;
+
+pub const GET_ARCHIVED_RESPONSE: &str = r#"
+SELECT response
+FROM api_responses
+WHERE endpoint = $1
+  AND params = $2
+"#;
@@ -0,0 +1,25 @@
+pub const UPSERT_COMPETITION: &str = r#"
+INSERT INTO competitions (league_id, competition_name, country_code, gender, league_type, tier, competition_type, season_format)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+ON CONFLICT (league_id) DO UPDATE SET
+    competition_name = EXCLUDED.competition_name,
+    country_code = EXCLUDED.country_code,
+    gender = EXCLUDED.gender,
+    league_type = EXCLUDED.league_type,
+    tier = EXCLUDED.tier,
+    competition_type = COALESCE(EXCLUDED.competition_type, competitions.competition_type),
+    season_format = COALESCE(EXCLUDED.season_format, competitions.season_format),
+    updated_at_utc = NOW()
+"#;
+
+// Every configured competition gets a row so every match has a competition
+// to join to. A competition_type set in config wins over the one fbrapi
+// reports, as fbrapi does not list continental and international ones.
+pub const UPSERT_CONFIGURED_COMPETITION: &str = r#"
+INSERT INTO competitions (league_id, competition_name, competition_type, season_format)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT (league_id) DO UPDATE SET
+    competition_type = COALESCE(EXCLUDED.competition_type, competitions.competition_type),
+    season_format = COALESCE(competitions.season_format, EXCLUDED.season_format),
+    updated_at_utc = NOW()
+"#;
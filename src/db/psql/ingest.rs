@@ -0,0 +1,24 @@
+// Each statement writes every row of a match at once from parallel arrays,
+// one element per row, so a match is a few round trips however many
+// players and variables it has. ON CONFLICT DO NOTHING keeps the behaviour
+// of the single-row inserts these replaced.
+pub const INSERT_PLAYERS: &str = r#"
+INSERT INTO players (player_id, full_name, nationality)
+SELECT *
+FROM UNNEST($1::VARCHAR[], $2::VARCHAR[], $3::VARCHAR[])
+ON CONFLICT (player_id) DO NOTHING
+"#;
+
+pub const INSERT_PLAYER_STATS: &str = r#"
+INSERT INTO player_stats (match_id, team_id, player_id, variable, value, value_text)
+SELECT $1, *
+FROM UNNEST($2::VARCHAR[], $3::VARCHAR[], $4::VARCHAR[], $5::FLOAT8[], $6::VARCHAR[])
+ON CONFLICT DO NOTHING
+"#;
+
+pub const INSERT_APPEARANCES: &str = r#"
+INSERT INTO appearances (match_id, team_id, player_id, minutes, started, goals, assists, position)
+SELECT $1, *
+FROM UNNEST($2::VARCHAR[], $3::VARCHAR[], $4::INT[], $5::BOOLEAN[], $6::INT[], $7::INT[], $8::VARCHAR[])
+ON CONFLICT (match_id, player_id) DO NOTHING
+"#;
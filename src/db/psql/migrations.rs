@@ -0,0 +1,123 @@
+pub const CREATE_MIGRATIONS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS schema_migrations (
+   version INT PRIMARY KEY,
+   name VARCHAR(255) NOT NULL,
+   checksum CHAR(64) NOT NULL,
+   applied_at_utc TIMESTAMP DEFAULT NOW()
+);
+"#;
+
+pub const MIGRATIONS_TABLE_EXISTS: &str = r#"
+SELECT to_regclass('schema_migrations') IS NOT NULL AS tracked
+"#;
+
+pub const GET_SCHEMA_VERSION: &str = r#"
+SELECT COALESCE(MAX(version), 0) AS version
+FROM schema_migrations
+"#;
+
+pub const LIST_APPLIED_MIGRATIONS: &str = r#"
+SELECT version, name, checksum, applied_at_utc
+FROM schema_migrations
+ORDER BY version
+"#;
+
+pub const RECORD_MIGRATION: &str = r#"
+INSERT INTO schema_migrations (version, name, checksum)
+VALUES ($1, $2, $3)
+"#;
+
+// Migration 4. The server logs in as a member of cnxns_readonly, which can
+// read everything in the schema, including tables added by later
+// migrations, and write nothing.
+pub const CREATE_READONLY_ROLE: &str = r#"
+DO $$
+BEGIN
+   IF NOT EXISTS (SELECT 1 FROM pg_roles WHERE rolname = 'cnxns_readonly') THEN
+      CREATE ROLE cnxns_readonly NOLOGIN;
+   END IF;
+   EXECUTE format('GRANT USAGE ON SCHEMA %I TO cnxns_readonly', current_schema());
+   EXECUTE format('GRANT SELECT ON ALL TABLES IN SCHEMA %I TO cnxns_readonly', current_schema());
+   EXECUTE format('ALTER DEFAULT PRIVILEGES IN SCHEMA %I GRANT SELECT ON TABLES TO cnxns_readonly', current_schema());
+END
+$$;
+"#;
+
+// Migration 5. One typed row per player per match for the game queries,
+// copied from player_stats, which keeps the full set of stat variables.
+// matches is keyed by league and season first, so lookups by match_id get
+// their own index.
+pub const CREATE_APPEARANCES_TABLE: &str = r#"
+CREATE TABLE appearances (
+   match_id VARCHAR(80) NOT NULL,
+   team_id VARCHAR(80) NOT NULL,
+   player_id VARCHAR(80) NOT NULL REFERENCES players(player_id),
+   minutes INT NOT NULL DEFAULT 0,
+   started BOOLEAN,
+   goals INT NOT NULL DEFAULT 0,
+   assists INT NOT NULL DEFAULT 0,
+   position VARCHAR(255),
+   created_at_utc TIMESTAMP DEFAULT NOW(),
+   PRIMARY KEY (match_id, player_id)
+);
+
+CREATE INDEX appearances_player_idx ON appearances (player_id, match_id, team_id);
+CREATE INDEX IF NOT EXISTS matches_match_id_idx ON matches (match_id);
+
+INSERT INTO appearances (match_id, team_id, player_id, minutes, started, goals, assists, position)
+SELECT
+   match_id,
+   MIN(team_id),
+   player_id,
+   COALESCE(MAX(value) FILTER (WHERE variable = 'mins_played'), 0)::INT,
+   MAX(value) FILTER (WHERE variable = 'started') > 0,
+   COALESCE(MAX(value) FILTER (WHERE variable = 'goals'), 0)::INT,
+   COALESCE(MAX(value) FILTER (WHERE variable = 'assists'), 0)::INT,
+   MAX(value_text) FILTER (WHERE variable = 'position')
+FROM player_stats
+GROUP BY match_id, player_id
+HAVING COUNT(*) FILTER (WHERE variable = 'mins_played') > 0;
+"#;
+
+// Migration 6. One row per scraper or daily run, written at the start and
+// completed when the run ends
+pub const CREATE_SCRAPE_RUNS_TABLE: &str = r#"
+CREATE TABLE scrape_runs (
+   run_id SERIAL PRIMARY KEY,
+   job VARCHAR(20) NOT NULL,
+   command VARCHAR(255) NOT NULL,
+   config_hash CHAR(64),
+   status VARCHAR(20) NOT NULL DEFAULT 'running'
+      CHECK (status IN ('running', 'succeeded', 'failed', 'abandoned')),
+   matches_attempted INT NOT NULL DEFAULT 0,
+   matches_succeeded INT NOT NULL DEFAULT 0,
+   matches_failed INT NOT NULL DEFAULT 0,
+   error_summary TEXT,
+   started_at_utc TIMESTAMP DEFAULT NOW(),
+   finished_at_utc TIMESTAMP
+);
+
+CREATE INDEX scrape_runs_started_idx ON scrape_runs (started_at_utc DESC);
+"#;
+
+// Migration 7. fbrapi gives no lineup, so started was guessed from the order
+// of its player list. The guesses are cleared rather than kept as fact.
+pub const CLEAR_FBRAPI_STARTED: &str = r#"
+DELETE FROM player_stats ps
+USING matches m
+WHERE ps.match_id = m.match_id
+   AND ps.variable = 'started'
+   AND m.scrape_source IN ('fbrapi', 'archive');
+
+UPDATE appearances a
+SET started = NULL
+FROM matches m
+WHERE a.match_id = m.match_id
+   AND m.scrape_source IN ('fbrapi', 'archive');
+"#;
+
+// Migration 8. The command is recorded as typed, options and all, so it is
+// not capped at a length.
+pub const WIDEN_RUN_COMMAND: &str = r#"
+ALTER TABLE scrape_runs ALTER COLUMN command TYPE TEXT;
+"#;
@@ -1,3 +1,14 @@
+pub mod archive;
+pub mod competitions;
+pub mod ingest;
+pub mod migrations;
+pub mod players;
+pub mod quarantine;
+pub mod refresh;
+pub mod repair;
+pub mod runs;
 pub mod schema;
 pub mod scraping;
+pub mod seasons;
+pub mod stat_variables;
 pub mod update_scrape_status;
@@ -0,0 +1,44 @@
+// Players without a profile yet, most recently seen first so current players
+// are enriched before the back catalogue
+pub const LIST_PLAYERS_TO_ENRICH: &str = r#"
+SELECT player_id, full_name
+FROM players
+WHERE profile_fetched_at_utc IS NULL
+ORDER BY last_appearance_date DESC NULLS LAST, player_id
+"#;
+
+// Nationality from match data is a country code, so the profile only fills
+// it in where match data had none
+pub const UPDATE_PLAYER_PROFILE: &str = r#"
+UPDATE players
+SET birth_date = $2,
+    position = $3,
+    footed = $4,
+    nationality = COALESCE(nationality, $5),
+    profile_fetched_at_utc = NOW(),
+    updated_at_utc = NOW()
+WHERE player_id = $1
+"#;
+
+// A player's current club is the team of their latest appearance, and they
+// count as active if that appearance was within the last year
+pub const DERIVE_PLAYER_CLUBS: &str = r#"
+WITH latest_appearances AS (
+    SELECT DISTINCT ON (a.player_id)
+        a.player_id,
+        a.team_id,
+        m.match_date
+    FROM appearances a
+    JOIN matches m ON m.match_id = a.match_id
+    ORDER BY a.player_id, m.match_date DESC, m.match_id DESC
+)
+UPDATE players p
+SET current_club = la.team_id,
+    last_appearance_date = la.match_date,
+    active = la.match_date >= CURRENT_DATE - INTERVAL '1 year',
+    updated_at_utc = NOW()
+FROM latest_appearances la
+WHERE p.player_id = la.player_id
+  AND (p.current_club, p.last_appearance_date, p.active)
+      IS DISTINCT FROM (la.team_id, la.match_date, la.match_date >= CURRENT_DATE - INTERVAL '1 year')
+"#;
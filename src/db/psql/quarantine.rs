@@ -0,0 +1,43 @@
+pub const RECORD_SCRAPE_FAILURE: &str = r#"
+INSERT INTO scrape_failures (match_id, error_text, attempts, quarantined)
+VALUES ($1, $2, 1, TRUE)
+ON CONFLICT (match_id) DO UPDATE SET
+    error_text = EXCLUDED.error_text,
+    attempts = scrape_failures.attempts + 1,
+    quarantined = TRUE,
+    last_attempt_at_utc = NOW()
+"#;
+
+pub const RELEASE_QUARANTINED_MATCH: &str = r#"
+UPDATE scrape_failures
+SET quarantined = FALSE
+WHERE match_id = $1
+  AND quarantined
+"#;
+
+pub const LIST_QUARANTINED_MATCHES: &str = r#"
+SELECT sf.match_id, m.league_id, m.season_id, m.home_team_name, m.away_team_name,
+       sf.error_text, sf.attempts, sf.last_attempt_at_utc
+FROM scrape_failures sf
+LEFT JOIN matches m ON m.match_id = sf.match_id
+WHERE sf.quarantined
+ORDER BY sf.last_attempt_at_utc DESC
+"#;
+
+pub const MARK_MATCH_FAILED: &str = r#"
+UPDATE matches
+SET scrape_status = 'failed',
+    status_updated_at_utc = NOW(),
+    updated_at_utc = NOW()
+WHERE match_id = $1
+  AND scrape_status IN ('pending', 'fetched', 'failed')
+"#;
+
+pub const MARK_MATCH_RELEASED: &str = r#"
+UPDATE matches
+SET scrape_status = 'pending',
+    status_updated_at_utc = NOW(),
+    updated_at_utc = NOW()
+WHERE match_id = $1
+  AND scrape_status = 'failed'
+"#;
@@ -0,0 +1,52 @@
+pub const LIST_ONGOING_SEASONS: &str = r#"
+SELECT league_id, league_name, season_id
+FROM league_seasons
+WHERE ongoing
+ORDER BY league_id, start_date
+"#;
+
+pub const GET_SEASON_FIXTURES: &str = r#"
+SELECT match_id, match_date, scrape_status
+FROM matches
+WHERE league_id = $1
+  AND season_id = $2
+"#;
+
+// New fixtures come in at the scrape status their fixture status implies
+// ($13). Known fixtures take the latest date, teams, score and fixture
+// status; those still waiting to be fetched move to the new scrape status,
+// while anything further along keeps its own.
+pub const UPSERT_FIXTURE: &str = r#"
+INSERT INTO matches (league_id, season_id, match_id, home_team_id, home_team_name, away_team_id, away_team_name, match_date, fixture_status, home_score, away_score, fixture_notes, data_count, scrape_status)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, 0, $13)
+ON CONFLICT (league_id, season_id, match_id) DO UPDATE SET
+    home_team_id = EXCLUDED.home_team_id,
+    home_team_name = EXCLUDED.home_team_name,
+    away_team_id = EXCLUDED.away_team_id,
+    away_team_name = EXCLUDED.away_team_name,
+    match_date = EXCLUDED.match_date,
+    fixture_status = EXCLUDED.fixture_status,
+    home_score = EXCLUDED.home_score,
+    away_score = EXCLUDED.away_score,
+    fixture_notes = EXCLUDED.fixture_notes,
+    scrape_status = CASE
+        WHEN matches.scrape_status IN ('pending', 'not_yet_played') THEN EXCLUDED.scrape_status
+        ELSE matches.scrape_status
+    END,
+    status_updated_at_utc = CASE
+        WHEN matches.scrape_status IN ('pending', 'not_yet_played')
+         AND matches.scrape_status <> EXCLUDED.scrape_status THEN NOW()
+        ELSE matches.status_updated_at_utc
+    END,
+    updated_at_utc = NOW()
+"#;
+
+pub const UPDATE_REFRESHED_SEASON: &str = r#"
+UPDATE league_seasons
+SET number_matches = $3,
+    ongoing = $4,
+    fixtures_refreshed_at_utc = NOW(),
+    updated_at_utc = NOW()
+WHERE league_id = $1
+  AND season_id = $2
+"#;
@@ -0,0 +1,47 @@
+// $1 is the variable set every ingested match has carried, not the current
+// required flags, so making a variable required later does not make older
+// matches look partial
+pub const FIND_PARTIAL_MATCHES: &str = r#"
+WITH player_variables AS (
+    SELECT
+        ps.match_id,
+        ps.team_id,
+        ps.player_id,
+        COUNT(DISTINCT ps.variable) FILTER (WHERE ps.variable = ANY($1)) AS n_required
+    FROM player_stats ps
+    GROUP BY ps.match_id, ps.team_id, ps.player_id
+),
+match_summary AS (
+    SELECT
+        pv.match_id,
+        COUNT(DISTINCT pv.team_id) AS n_teams,
+        MIN(pv.n_required) AS min_required
+    FROM player_variables pv
+    GROUP BY pv.match_id
+)
+SELECT ms.match_id
+FROM match_summary ms
+WHERE ms.n_teams < 2
+   OR ms.min_required < cardinality($1)
+ORDER BY ms.match_id
+"#;
+
+pub const CLEAR_MATCH_STATS: &str = r#"
+DELETE FROM player_stats
+WHERE match_id = ANY($1)
+"#;
+
+pub const CLEAR_MATCH_APPEARANCES: &str = r#"
+DELETE FROM appearances
+WHERE match_id = ANY($1)
+"#;
+
+pub const RESET_MATCH_STATUS: &str = r#"
+UPDATE matches
+SET scrape_status = 'pending',
+    data_count = 0,
+    status_updated_at_utc = NOW(),
+    updated_at_utc = NOW()
+WHERE match_id = ANY($1)
+  AND scrape_status IN ('pending', 'fetched', 'ingested')
+"#;
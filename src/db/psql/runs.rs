@@ -0,0 +1,49 @@
+pub const TRY_LOCK_JOB: &str = r#"
+SELECT pg_try_advisory_lock($1, $2) AS locked
+"#;
+
+pub const TRY_LOCK_JOB_SHARED: &str = r#"
+SELECT pg_try_advisory_lock_shared($1, $2) AS locked
+"#;
+
+pub const UNLOCK_JOB: &str = r#"
+SELECT pg_advisory_unlock($1, $2)
+"#;
+
+pub const UNLOCK_JOB_SHARED: &str = r#"
+SELECT pg_advisory_unlock_shared($1, $2)
+"#;
+
+// Only called while holding the job's lock, so any run of the job still
+// marked running died without finishing
+pub const ABANDON_STALE_RUNS: &str = r#"
+UPDATE scrape_runs
+SET status = 'abandoned'
+WHERE job = $1
+  AND status = 'running'
+"#;
+
+pub const START_RUN: &str = r#"
+INSERT INTO scrape_runs (job, command, config_hash)
+VALUES ($1, $2, $3)
+RETURNING run_id
+"#;
+
+pub const FINISH_RUN: &str = r#"
+UPDATE scrape_runs
+SET status = $2,
+    matches_attempted = $3,
+    matches_succeeded = $4,
+    matches_failed = $5,
+    error_summary = $6,
+    finished_at_utc = NOW()
+WHERE run_id = $1
+"#;
+
+pub const LIST_RECENT_RUNS: &str = r#"
+SELECT run_id, job, command, status, matches_attempted, matches_succeeded, matches_failed,
+       error_summary, started_at_utc, finished_at_utc
+FROM scrape_runs
+ORDER BY started_at_utc DESC
+LIMIT $1
+"#;
@@ -1,5 +1,7 @@
+// Migration 1. Applied databases are checksummed against this text, so
+// schema changes go in a new migration instead.
 pub const CREATE_TABLES_SQL: &str = r#"
-CREATE TABLE IF NOT EXISTS connections.league_seasons (
+CREATE TABLE IF NOT EXISTS league_seasons (
    league_id VARCHAR(10),
    league_name VARCHAR(255),
    season_id VARCHAR(50),
@@ -10,7 +12,7 @@ CREATE TABLE IF NOT EXISTS connections.league_seasons (
    PRIMARY KEY (league_id, season_id)
 );
 
-CREATE TABLE IF NOT EXISTS connections.matches (
+CREATE TABLE IF NOT EXISTS matches (
    league_id VARCHAR(10),
    season_id VARCHAR(50),
    match_id VARCHAR(80),
@@ -23,10 +25,10 @@ CREATE TABLE IF NOT EXISTS connections.matches (
    created_at_utc TIMESTAMP DEFAULT NOW(),
    updated_at_utc TIMESTAMP DEFAULT NOW(),
    PRIMARY KEY (league_id, season_id, match_id),
-   FOREIGN KEY (league_id, season_id) REFERENCES connections.league_seasons(league_id, season_id)
+   FOREIGN KEY (league_id, season_id) REFERENCES league_seasons(league_id, season_id)
 );
 
-CREATE TABLE IF NOT EXISTS connections.players (
+CREATE TABLE IF NOT EXISTS players (
    player_id VARCHAR(80) PRIMARY KEY,
    full_name VARCHAR(255),
    nationality VARCHAR(10),
@@ -36,7 +38,7 @@ CREATE TABLE IF NOT EXISTS connections.players (
    updated_at_utc TIMESTAMP DEFAULT NOW()
 );
 
-CREATE TABLE IF NOT EXISTS connections.player_stats (
+CREATE TABLE IF NOT EXISTS player_stats (
    match_id VARCHAR(80),
    team_id VARCHAR(80),
    player_id VARCHAR(80),
@@ -45,6 +47,140 @@ CREATE TABLE IF NOT EXISTS connections.player_stats (
    created_at_utc TIMESTAMP DEFAULT NOW(),
    updated_at_utc TIMESTAMP DEFAULT NOW(),
    PRIMARY KEY (match_id, team_id, player_id, variable),
-   FOREIGN KEY (player_id) REFERENCES connections.players(player_id)
+   FOREIGN KEY (player_id) REFERENCES players(player_id)
 );
+
+ALTER TABLE player_stats ADD COLUMN IF NOT EXISTS value_text VARCHAR(255);
+
+ALTER TABLE players
+   ADD COLUMN IF NOT EXISTS birth_date DATE,
+   ADD COLUMN IF NOT EXISTS position VARCHAR(20),
+   ADD COLUMN IF NOT EXISTS footed VARCHAR(10),
+   ADD COLUMN IF NOT EXISTS last_appearance_date DATE,
+   ADD COLUMN IF NOT EXISTS profile_fetched_at_utc TIMESTAMP;
+
+ALTER TABLE league_seasons
+   ADD COLUMN IF NOT EXISTS ongoing BOOLEAN DEFAULT FALSE,
+   ADD COLUMN IF NOT EXISTS fixtures_refreshed_at_utc TIMESTAMP;
+
+-- Seasons as years and nominal dates, July to June for split-year seasons
+-- and January to December for calendar-year ones, so they sort and compare
+-- without parsing season_id
+ALTER TABLE league_seasons
+   ADD COLUMN IF NOT EXISTS start_year INT GENERATED ALWAYS AS (
+      CASE WHEN season_id ~ '^\d{4}(-\d{4})?$' THEN LEFT(season_id, 4)::INT END
+   ) STORED,
+   ADD COLUMN IF NOT EXISTS end_year INT GENERATED ALWAYS AS (
+      CASE WHEN season_id ~ '^\d{4}(-\d{4})?$' THEN RIGHT(season_id, 4)::INT END
+   ) STORED,
+   ADD COLUMN IF NOT EXISTS start_date DATE GENERATED ALWAYS AS (
+      CASE
+         WHEN season_id ~ '^\d{4}-\d{4}$' THEN make_date(LEFT(season_id, 4)::INT, 7, 1)
+         WHEN season_id ~ '^\d{4}$' THEN make_date(season_id::INT, 1, 1)
+      END
+   ) STORED,
+   ADD COLUMN IF NOT EXISTS end_date DATE GENERATED ALWAYS AS (
+      CASE
+         WHEN season_id ~ '^\d{4}-\d{4}$' THEN make_date(RIGHT(season_id, 4)::INT, 6, 30)
+         WHEN season_id ~ '^\d{4}$' THEN make_date(season_id::INT, 12, 31)
+      END
+   ) STORED;
+
+ALTER TABLE matches
+   ADD COLUMN IF NOT EXISTS scrape_status VARCHAR(20) DEFAULT 'pending'
+      CHECK (scrape_status IN ('pending', 'fetched', 'ingested', 'failed', 'skipped', 'not_yet_played')),
+   ADD COLUMN IF NOT EXISTS scrape_source VARCHAR(20)
+      CHECK (scrape_source IN ('fbrapi', 'fbref', 'archive')),
+   ADD COLUMN IF NOT EXISTS fetched_at_utc TIMESTAMP,
+   ADD COLUMN IF NOT EXISTS ingested_at_utc TIMESTAMP,
+   ADD COLUMN IF NOT EXISTS status_updated_at_utc TIMESTAMP DEFAULT NOW(),
+   ADD COLUMN IF NOT EXISTS fixture_status VARCHAR(20)
+      CHECK (fixture_status IN ('scheduled', 'completed', 'postponed', 'abandoned')),
+   ADD COLUMN IF NOT EXISTS home_score INT,
+   ADD COLUMN IF NOT EXISTS away_score INT,
+   ADD COLUMN IF NOT EXISTS fixture_notes VARCHAR(255);
+
+CREATE OR REPLACE VIEW league_season_status AS
+SELECT
+   ls.league_id,
+   ls.league_name,
+   ls.season_id,
+   ls.number_matches,
+   COUNT(m.match_id) AS matches_known,
+   COUNT(m.match_id) FILTER (WHERE m.scrape_status = 'pending') AS matches_pending,
+   COUNT(m.match_id) FILTER (WHERE m.scrape_status = 'fetched') AS matches_fetched,
+   COUNT(m.match_id) FILTER (WHERE m.scrape_status = 'ingested') AS matches_ingested,
+   COUNT(m.match_id) FILTER (WHERE m.scrape_status = 'failed') AS matches_failed,
+   COUNT(m.match_id) FILTER (WHERE m.scrape_status = 'skipped') AS matches_skipped,
+   COUNT(m.match_id) FILTER (WHERE m.scrape_status = 'not_yet_played') AS matches_not_yet_played,
+   MAX(m.status_updated_at_utc) AS status_updated_at_utc,
+   COUNT(m.match_id) FILTER (WHERE m.fixture_status = 'postponed') AS matches_postponed,
+   COUNT(m.match_id) FILTER (WHERE m.fixture_status = 'abandoned') AS matches_abandoned
+FROM league_seasons ls
+LEFT JOIN matches m
+   ON m.league_id = ls.league_id
+  AND m.season_id = ls.season_id
+GROUP BY ls.league_id, ls.league_name, ls.season_id, ls.number_matches;
+
+CREATE TABLE IF NOT EXISTS competitions (
+   league_id VARCHAR(10) PRIMARY KEY,
+   competition_name VARCHAR(255),
+   country_code VARCHAR(10),
+   gender VARCHAR(1),
+   league_type VARCHAR(50),
+   tier VARCHAR(10),
+   created_at_utc TIMESTAMP DEFAULT NOW(),
+   updated_at_utc TIMESTAMP DEFAULT NOW()
+);
+
+ALTER TABLE competitions
+   ADD COLUMN IF NOT EXISTS competition_type VARCHAR(20)
+      CHECK (competition_type IN ('domestic_league', 'domestic_cup', 'continental_club', 'international')),
+   ADD COLUMN IF NOT EXISTS season_format VARCHAR(20)
+      CHECK (season_format IN ('split_year', 'calendar_year'));
+
+CREATE TABLE IF NOT EXISTS competition_seasons (
+   league_id VARCHAR(10),
+   season_id VARCHAR(50),
+   discovered_at_utc TIMESTAMP DEFAULT NOW(),
+   PRIMARY KEY (league_id, season_id)
+);
+
+CREATE TABLE IF NOT EXISTS stat_variables (
+   variable VARCHAR(255) PRIMARY KEY,
+   value_type VARCHAR(20) CHECK (value_type IN ('count', 'decimal', 'flag', 'text')),
+   api_source VARCHAR(255),
+   fbref_source VARCHAR(255),
+   required BOOLEAN DEFAULT FALSE,
+   description TEXT,
+   created_at_utc TIMESTAMP DEFAULT NOW(),
+   updated_at_utc TIMESTAMP DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS api_responses (
+   endpoint VARCHAR(255),
+   params JSONB,
+   response JSONB,
+   fetched_at_utc TIMESTAMP DEFAULT NOW(),
+   PRIMARY KEY (endpoint, params)
+);
+
+CREATE TABLE IF NOT EXISTS scrape_failures (
+   match_id VARCHAR(80) PRIMARY KEY,
+   error_text TEXT,
+   attempts INT DEFAULT 1,
+   quarantined BOOLEAN DEFAULT TRUE,
+   first_failed_at_utc TIMESTAMP DEFAULT NOW(),
+   last_attempt_at_utc TIMESTAMP DEFAULT NOW()
+);
+
+-- matches that were skipped by hand before quarantine was tracked
+INSERT INTO scrape_failures (match_id, error_text)
+VALUES
+   ('19bad36c', 'Known problematic match'),
+   ('93a55635', 'Known problematic match'),
+   ('7110621d', 'Known problematic match'),
+   ('8d12dd69', 'Known problematic match'),
+   ('ff278feb', 'Known problematic match')
+ON CONFLICT (match_id) DO NOTHING;
 "#;
@@ -1,9 +1,9 @@
 pub const UNSCRAPED_MATCHES: &str = r#"
 SELECT match_id
-FROM connections.matches m 
+FROM matches m 
 WHERE NOT EXISTS (
-  SELECT * FROM connections.player_stats ps 
-  WHERE m.match_id = ps.match_id
+  SELECT * FROM appearances a
+  WHERE m.match_id = a.match_id
 )
 ORDER BY league_id, season_id    
 "#;
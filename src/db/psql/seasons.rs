@@ -0,0 +1,13 @@
+pub const UPSERT_COMPETITION_SEASON: &str = r#"
+INSERT INTO competition_seasons (league_id, season_id)
+VALUES ($1, $2)
+ON CONFLICT (league_id, season_id) DO UPDATE SET
+    discovered_at_utc = NOW()
+"#;
+
+pub const LIST_COMPETITION_SEASONS: &str = r#"
+SELECT season_id
+FROM competition_seasons
+WHERE league_id = $1
+ORDER BY season_id
+"#;
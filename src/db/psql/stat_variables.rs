@@ -0,0 +1,11 @@
+pub const UPSERT_STAT_VARIABLE: &str = r#"
+INSERT INTO stat_variables (variable, value_type, api_source, fbref_source, required, description)
+VALUES ($1, $2, $3, $4, $5, $6)
+ON CONFLICT (variable) DO UPDATE SET
+    value_type = EXCLUDED.value_type,
+    api_source = EXCLUDED.api_source,
+    fbref_source = EXCLUDED.fbref_source,
+    required = EXCLUDED.required,
+    description = EXCLUDED.description,
+    updated_at_utc = NOW()
+"#;
@@ -1,32 +1,116 @@
-pub const CHECK_SCRAPING_COUNTS: &str = r#"
+// Brings scrape_status in line with what is already in the database: matches
+// scraped before statuses were tracked, quarantined matches, and fixtures
+// the fixture list reports as played, unplayed or abandoned. Fixtures stored
+// before fixture statuses existed are taken at their date. Seasons with
+// fixtures still to play are marked ongoing so the refresh command picks them
+// up. data_count is kept as a mirror of the ingested status for older queries.
+pub const SYNC_SCRAPE_STATUS: &str = r#"
 WITH scraped_matches AS (
-    SELECT 
-        count(*) as original_count,
-        count(*) / 3 as count_divided_by_3,
-        count(*) % 3 = 0 as is_integer_division,
-        m.match_id
-    FROM connections.matches m 
-    JOIN connections.player_stats ps
-        ON ps.match_id = m.match_id 
-    GROUP BY m.match_id
+    SELECT a.match_id
+    FROM appearances a
+    GROUP BY a.match_id
+    HAVING COUNT(DISTINCT a.team_id) = 2
 )
-UPDATE connections.matches 
-SET data_count = 1,
-    updated_at_utc = NOW()
+UPDATE matches m
+SET scrape_status = 'ingested',
+    ingested_at_utc = COALESCE(m.ingested_at_utc, m.updated_at_utc),
+    status_updated_at_utc = NOW()
 FROM scraped_matches sm
-WHERE connections.matches.match_id = sm.match_id
-  AND sm.original_count > 0 
-  AND sm.is_integer_division = true;
+WHERE m.match_id = sm.match_id
+  AND m.scrape_status IN ('pending', 'fetched');
+
+UPDATE matches m
+SET scrape_status = 'failed',
+    status_updated_at_utc = NOW()
+FROM scrape_failures sf
+WHERE m.match_id = sf.match_id
+  AND sf.quarantined
+  AND m.scrape_status IN ('pending', 'fetched');
+
+UPDATE matches
+SET fixture_status = CASE
+        WHEN scrape_status = 'ingested' OR match_date <= CURRENT_DATE THEN 'completed'
+        ELSE 'scheduled'
+    END
+WHERE fixture_status IS NULL;
+
+UPDATE matches
+SET scrape_status = 'not_yet_played',
+    status_updated_at_utc = NOW()
+WHERE scrape_status = 'pending'
+  AND (match_date > CURRENT_DATE OR fixture_status IN ('scheduled', 'postponed'));
+
+UPDATE matches
+SET scrape_status = 'skipped',
+    status_updated_at_utc = NOW()
+WHERE scrape_status IN ('pending', 'not_yet_played')
+  AND fixture_status = 'abandoned';
+
+UPDATE matches
+SET scrape_status = 'pending',
+    status_updated_at_utc = NOW()
+WHERE scrape_status = 'not_yet_played'
+  AND fixture_status = 'completed'
+  AND match_date <= CURRENT_DATE;
+
+UPDATE league_seasons ls
+SET ongoing = TRUE,
+    updated_at_utc = NOW()
+WHERE NOT COALESCE(ls.ongoing, FALSE)
+  AND EXISTS (
+    SELECT 1
+    FROM matches m
+    WHERE m.league_id = ls.league_id
+      AND m.season_id = ls.season_id
+      AND m.scrape_status = 'not_yet_played'
+  );
+
+UPDATE matches
+SET data_count = CASE WHEN scrape_status = 'ingested' THEN 1 ELSE 0 END
+WHERE data_count IS DISTINCT FROM CASE WHEN scrape_status = 'ingested' THEN 1 ELSE 0 END;
 
-UPDATE connections.league_seasons ls
-SET data_count = matches_summary.matches_with_data,
+UPDATE league_seasons ls
+SET data_count = lss.matches_ingested,
     updated_at_utc = NOW()
-FROM (
-    SELECT m.league_id, m.season_id, COUNT(DISTINCT m.match_id) as matches_with_data
-    FROM connections.matches m
-    WHERE m.data_count = 1
-    GROUP BY m.league_id, m.season_id
-) matches_summary
-WHERE ls.league_id = matches_summary.league_id
-  AND ls.season_id = matches_summary.season_id;
+FROM league_season_status lss
+WHERE ls.league_id = lss.league_id
+  AND ls.season_id = lss.season_id
+  AND ls.data_count IS DISTINCT FROM lss.matches_ingested;
+"#;
+
+// Only moves a match on if its current status is one of $4, so an
+// out-of-order update fails loudly instead of rewinding progress
+pub const SET_MATCH_STATUS: &str = r#"
+UPDATE matches
+SET scrape_status = $2::VARCHAR,
+    scrape_source = COALESCE($3, scrape_source),
+    fetched_at_utc = CASE WHEN $2::VARCHAR = 'fetched' THEN NOW() ELSE fetched_at_utc END,
+    ingested_at_utc = CASE WHEN $2::VARCHAR = 'ingested' THEN NOW() ELSE ingested_at_utc END,
+    status_updated_at_utc = NOW(),
+    updated_at_utc = NOW()
+WHERE match_id = $1
+  AND scrape_status = ANY($4)
+"#;
+
+pub const GET_MATCH_STATUS: &str = r#"
+SELECT scrape_status
+FROM matches
+WHERE match_id = $1
+"#;
+
+pub const GET_LEAGUE_SEASON_STATUS: &str = r#"
+SELECT *
+FROM league_season_status
+WHERE league_id = $1
+  AND season_id = $2
+"#;
+
+// NULL filters match every league or season
+pub const LIST_LEAGUE_SEASON_STATUS: &str = r#"
+SELECT lss.*
+FROM league_season_status lss
+JOIN league_seasons ls USING (league_id, season_id)
+WHERE ($1::TEXT IS NULL OR lss.league_id = $1)
+  AND ($2::TEXT IS NULL OR lss.season_id = $2)
+ORDER BY lss.league_id, ls.start_date
 "#;
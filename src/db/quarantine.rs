@@ -0,0 +1,95 @@
+use crate::db::psql::quarantine::{
+    LIST_QUARANTINED_MATCHES, MARK_MATCH_FAILED, MARK_MATCH_RELEASED, RECORD_SCRAPE_FAILURE,
+    RELEASE_QUARANTINED_MATCH,
+};
+use chrono::NaiveDateTime;
+use postgres::Client as PgClient;
+
+pub struct QuarantinedMatch {
+    pub match_id: String,
+    pub league_id: Option<String>,
+    pub season_id: Option<String>,
+    pub fixture: Option<String>,
+    pub error_text: Option<String>,
+    pub attempts: i32,
+    pub last_attempt_at_utc: Option<NaiveDateTime>,
+}
+
+// Only matches whose data cannot be stored belong in quarantine. A database
+// error says nothing about the match, so it stops the run instead of
+// quarantining every match it touches.
+pub fn is_database_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    error.downcast_ref::<postgres::Error>().is_some()
+}
+
+// Quarantined matches are marked failed and left out of get_match_info
+// until released back to pending
+pub fn record_failure(
+    db_client: &mut PgClient,
+    match_id: &str,
+    error_text: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut transaction = db_client.transaction()?;
+    transaction.execute(RECORD_SCRAPE_FAILURE, &[&match_id, &error_text])?;
+    transaction.execute(MARK_MATCH_FAILED, &[&match_id])?;
+    transaction.commit()?;
+    Ok(())
+}
+
+pub fn release_match(
+    db_client: &mut PgClient,
+    match_id: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut transaction = db_client.transaction()?;
+    let updated = transaction.execute(RELEASE_QUARANTINED_MATCH, &[&match_id])?;
+    transaction.execute(MARK_MATCH_RELEASED, &[&match_id])?;
+    transaction.commit()?;
+    Ok(updated > 0)
+}
+
+pub fn list_quarantined(
+    db_client: &mut PgClient,
+) -> Result<Vec<QuarantinedMatch>, Box<dyn std::error::Error>> {
+    let rows = db_client.query(LIST_QUARANTINED_MATCHES, &[])?;
+
+    let matches = rows
+        .iter()
+        .map(|row| {
+            let home: Option<String> = row.get("home_team_name");
+            let away: Option<String> = row.get("away_team_name");
+            QuarantinedMatch {
+                match_id: row.get("match_id"),
+                league_id: row.get("league_id"),
+                season_id: row.get("season_id"),
+                fixture: home
+                    .zip(away)
+                    .map(|(home, away)| format!("{} v {}", home, away)),
+                error_text: row.get("error_text"),
+                attempts: row.get::<_, Option<i32>>("attempts").unwrap_or(0),
+                last_attempt_at_utc: row.get("last_attempt_at_utc"),
+            }
+        })
+        .collect();
+    Ok(matches)
+}
+
+pub fn print_quarantined(db_client: &mut PgClient) -> Result<(), Box<dyn std::error::Error>> {
+    let matches = list_quarantined(db_client)?;
+    println!("{} quarantined matches", matches.len());
+    for quarantined in matches {
+        println!(
+            "{} | {} {} | {} | attempts: {} | last attempt: {} | {}",
+            quarantined.match_id,
+            quarantined.league_id.as_deref().unwrap_or("-"),
+            quarantined.season_id.as_deref().unwrap_or("-"),
+            quarantined.fixture.as_deref().unwrap_or("unknown fixture"),
+            quarantined.attempts,
+            quarantined
+                .last_attempt_at_utc
+                .map(|t| t.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            quarantined.error_text.as_deref().unwrap_or(""),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use crate::config::Config;
+use std::sync::{Mutex, OnceLock};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// One request's worth of allowance per interval, refilled continuously, so
+// time spent parsing and writing between requests counts towards the wait
+pub struct TokenBucket {
+    interval: Duration,
+    capacity: f64,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(interval: Duration, capacity: u32) -> TokenBucket {
+        TokenBucket {
+            interval,
+            capacity: capacity as f64,
+            state: Mutex::new(BucketState {
+                tokens: capacity as f64,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    // Blocks until a request may be sent. The lock is held while waiting, so
+    // callers on other threads queue up behind it in turn.
+    pub fn acquire(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            let now = Instant::now();
+            let refilled = now.duration_since(state.refilled_at).as_secs_f64()
+                / self.interval.as_secs_f64().max(f64::EPSILON);
+            state.tokens = (state.tokens + refilled).min(self.capacity);
+            state.refilled_at = now;
+
+            if state.tokens >= 1.0 {
+                state.tokens -= 1.0;
+                return;
+            }
+            sleep(self.interval.mul_f64(1.0 - state.tokens));
+        }
+    }
+}
+
+// Separate allowances for fbrapi and FBref, built from the config once per
+// process and shared by every outbound request, retries included
+pub struct RateLimiter {
+    fbrapi: TokenBucket,
+    fbref: TokenBucket,
+}
+
+// A capacity of one never lets a quiet spell turn into a burst above the
+// configured rate
+const BUCKET_CAPACITY: u32 = 1;
+
+static RATE_LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+
+impl RateLimiter {
+    pub fn for_config(config: &Config) -> &'static RateLimiter {
+        RATE_LIMITER.get_or_init(|| RateLimiter {
+            fbrapi: TokenBucket::new(
+                Duration::from_millis(config.fbref_api.rate_limit_ms),
+                BUCKET_CAPACITY,
+            ),
+            fbref: TokenBucket::new(
+                Duration::from_secs(config.scraping_config.rate_limit_seconds),
+                BUCKET_CAPACITY,
+            ),
+        })
+    }
+
+    pub fn fbrapi(&self) -> &TokenBucket {
+        &self.fbrapi
+    }
+
+    pub fn fbref(&self) -> &TokenBucket {
+        &self.fbref
+    }
+}
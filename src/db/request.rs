@@ -0,0 +1,227 @@
+use crate::db::rate_limit::TokenBucket;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::fmt;
+use std::thread::sleep;
+use std::time::Duration;
+
+const BASE_BACKOFF_MS: u64 = 1000;
+const MAX_BACKOFF_MS: u64 = 60_000;
+
+// Transient failures (5xx, 429, timeouts) are worth retrying, permanent ones
+// (other 4xx, malformed JSON) will fail the same way every time. A rejected
+// API key (401, 403) fails every request until the key is replaced.
+#[derive(Debug)]
+pub enum RequestError {
+    Transient(String),
+    Permanent(String),
+    Unauthorized(String),
+}
+
+impl RequestError {
+    pub fn is_permanent(&self) -> bool {
+        matches!(self, RequestError::Permanent(_))
+    }
+
+    fn is_retryable(&self) -> bool {
+        matches!(self, RequestError::Transient(_))
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::Transient(message) => write!(f, "transient failure: {}", message),
+            RequestError::Permanent(message) => write!(f, "permanent failure: {}", message),
+            RequestError::Unauthorized(message) => write!(f, "unauthorized: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+// True for request failures that outlasted their retries, which point at the
+// data source being down rather than at the match itself
+pub fn is_transient(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<RequestError>()
+        .is_some_and(|e| !e.is_permanent())
+}
+
+// Every later request would be refused the same way, so runs stop on these
+// even when continuing past other failures
+pub fn is_unauthorized(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<RequestError>()
+        .is_some_and(|e| matches!(e, RequestError::Unauthorized(_)))
+}
+
+// Every attempt, retries included, waits for the data source's rate limit
+pub fn send_with_retries(
+    request: RequestBuilder,
+    rate_limit: &TokenBucket,
+    max_retries: u32,
+) -> Result<Response, RequestError> {
+    let mut attempt = 0;
+    loop {
+        rate_limit.acquire();
+        let this_request = request.try_clone().ok_or(RequestError::Permanent(
+            "Request cannot be retried".to_string(),
+        ))?;
+
+        let (error, retry_after) = match this_request.send() {
+            Ok(response) if response.status().is_success() => return Ok(response),
+            Ok(response) => classify_status(response),
+            Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => (
+                RequestError::Transient(format!("HTTP request failed: {}", e)),
+                None,
+            ),
+            Err(e) => (
+                RequestError::Permanent(format!("HTTP request failed: {}", e)),
+                None,
+            ),
+        };
+
+        if !error.is_retryable() || attempt >= max_retries {
+            return Err(error);
+        }
+
+        let wait = retry_after.unwrap_or_else(|| backoff(attempt));
+        attempt += 1;
+        println!(
+            "{}, retry {}/{} in {:.1}s",
+            error,
+            attempt,
+            max_retries,
+            wait.as_secs_f64()
+        );
+        sleep(wait);
+    }
+}
+
+pub fn send_json(
+    request: RequestBuilder,
+    rate_limit: &TokenBucket,
+    max_retries: u32,
+) -> Result<Value, RequestError> {
+    let response = send_with_retries(request, rate_limit, max_retries)?;
+    let body = response
+        .text()
+        .map_err(|e| RequestError::Transient(format!("Failed to read response body: {}", e)))?;
+    serde_json::from_str(&body)
+        .map_err(|e| RequestError::Permanent(format!("Malformed JSON response: {}", e)))
+}
+
+pub fn send_text(
+    request: RequestBuilder,
+    rate_limit: &TokenBucket,
+    max_retries: u32,
+) -> Result<String, RequestError> {
+    let response = send_with_retries(request, rate_limit, max_retries)?;
+    response
+        .text()
+        .map_err(|e| RequestError::Transient(format!("Failed to read response body: {}", e)))
+}
+
+fn classify_status(response: Response) -> (RequestError, Option<Duration>) {
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_retry_after(value, Utc::now()));
+    let body = response
+        .text()
+        .unwrap_or_else(|_| "Could not read error body".to_string());
+    let message = format!(
+        "API request failed with status: {} - Body: {}",
+        status, body
+    );
+
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        (RequestError::Unauthorized(message), None)
+    } else if status == StatusCode::TOO_MANY_REQUESTS {
+        (RequestError::Transient(message), retry_after)
+    } else if status.is_server_error() || status == StatusCode::REQUEST_TIMEOUT {
+        (RequestError::Transient(message), None)
+    } else {
+        (RequestError::Permanent(message), None)
+    }
+}
+
+// Retry-After is either a number of seconds or an HTTP date. Either way the
+// wait is capped like any other backoff, so a large value cannot stall a run.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    let wait = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => {
+            let retry_at = DateTime::parse_from_rfc2822(value).ok()?;
+            (retry_at.with_timezone(&Utc) - now)
+                .to_std()
+                .unwrap_or(Duration::ZERO)
+        }
+    };
+    Some(wait.min(Duration::from_millis(MAX_BACKOFF_MS)))
+}
+
+// Exponential backoff with up to 50% jitter so retries from a stalled run
+// do not all land on the same second
+fn backoff(attempt: u32) -> Duration {
+    let base = BASE_BACKOFF_MS
+        .saturating_mul(2u64.saturating_pow(attempt))
+        .min(MAX_BACKOFF_MS);
+    let jitter = rand::thread_rng().gen_range(0..=base / 2);
+    Duration::from_millis(base + jitter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn retry_after_reads_seconds() {
+        assert_eq!(
+            parse_retry_after(" 30 ", now()),
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn retry_after_reads_http_dates() {
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:45 GMT", now()),
+            Some(Duration::from_secs(45))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now()),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn retry_after_is_capped() {
+        let cap = Some(Duration::from_millis(MAX_BACKOFF_MS));
+        assert_eq!(parse_retry_after("86400", now()), cap);
+        assert_eq!(
+            parse_retry_after("Thu, 22 Oct 2015 07:28:00 GMT", now()),
+            cap
+        );
+    }
+
+    #[test]
+    fn retry_after_ignores_garbage() {
+        assert_eq!(parse_retry_after("soon", now()), None);
+        assert_eq!(parse_retry_after("-5", now()), None);
+    }
+}
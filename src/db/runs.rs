@@ -0,0 +1,471 @@
+use crate::db::ingest::MatchInfo;
+use crate::db::psql::runs::{
+    ABANDON_STALE_RUNS, FINISH_RUN, LIST_RECENT_RUNS, START_RUN, TRY_LOCK_JOB, TRY_LOCK_JOB_SHARED,
+    UNLOCK_JOB, UNLOCK_JOB_SHARED,
+};
+use chrono::NaiveDateTime;
+use postgres::Client as PgClient;
+
+// First key of every advisory lock taken here, so they cannot collide with
+// locks taken by anything else sharing the database
+const LOCK_NAMESPACE: i32 = 0x636e78;
+
+// Failures listed in a run's error summary before it is cut short
+const MAX_SUMMARY_ERRORS: usize = 10;
+
+// Requests in a row that could not be served before the source is taken to
+// be down
+pub const MAX_CONSECUTIVE_UNAVAILABLE: u32 = 5;
+
+// Jobs that must not overlap. Every scraper command writing matches is a
+// scrape, and daily also holds a shared scrape lock so the daily selection
+// is never generated from a half-written season.
+#[derive(Clone, Copy)]
+pub enum Job {
+    Scrape,
+    Daily,
+}
+
+impl Job {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Job::Scrape => "scrape",
+            Job::Daily => "daily",
+        }
+    }
+
+    fn lock_key(&self) -> i32 {
+        match self {
+            Job::Scrape => 1,
+            Job::Daily => 2,
+        }
+    }
+
+    fn shared_locks(&self) -> &'static [Job] {
+        match self {
+            Job::Scrape => &[],
+            Job::Daily => &[Job::Scrape],
+        }
+    }
+}
+
+// A match whose data could not be stored. It is quarantined until released.
+pub struct MatchFailure {
+    pub league_id: String,
+    pub season_id: String,
+    pub match_id: String,
+    pub error: String,
+}
+
+#[derive(Default)]
+pub struct MatchCounts {
+    pub attempted: i32,
+    pub succeeded: i32,
+    pub failed: i32,
+    // Matches not fetched because the data source was down. That says
+    // nothing about the match, so they are left pending for the next run and
+    // kept out of the per league season failures.
+    pub unavailable: i32,
+    consecutive_unavailable: u32,
+    pub failures: Vec<MatchFailure>,
+}
+
+impl MatchCounts {
+    pub fn attempt(&mut self) {
+        self.attempted += 1;
+    }
+
+    pub fn success(&mut self) {
+        self.succeeded += 1;
+        self.consecutive_unavailable = 0;
+    }
+
+    pub fn failure(&mut self, match_info: &MatchInfo, error: &str) {
+        self.failed += 1;
+        self.consecutive_unavailable = 0;
+        self.failures.push(MatchFailure {
+            league_id: match_info.league_id.clone(),
+            season_id: match_info.season_id.clone(),
+            match_id: match_info.match_id.clone(),
+            error: error.to_string(),
+        });
+    }
+
+    pub fn source_unavailable(&mut self) {
+        self.unavailable += 1;
+        self.consecutive_unavailable += 1;
+    }
+
+    // Once this many matches in a row could not be fetched the source is
+    // taken to be down, and a run continuing past failures stops anyway
+    pub fn source_down(&self) -> bool {
+        self.consecutive_unavailable >= MAX_CONSECUTIVE_UNAVAILABLE
+    }
+
+    fn unfinished(&self) -> bool {
+        self.failed > 0 || self.unavailable > 0
+    }
+
+    fn unavailable_line(&self) -> Option<String> {
+        (self.unavailable > 0).then(|| {
+            format!(
+                "{} matches not fetched while the data source was unavailable, left pending",
+                self.unavailable
+            )
+        })
+    }
+
+    // Failures grouped by league season, in the order the seasons were
+    // scraped
+    fn by_league_season(&self) -> Vec<((&str, &str), Vec<&MatchFailure>)> {
+        let mut groups: Vec<((&str, &str), Vec<&MatchFailure>)> = Vec::new();
+        for failure in &self.failures {
+            let key = (failure.league_id.as_str(), failure.season_id.as_str());
+            match groups.iter_mut().find(|(group, _)| *group == key) {
+                Some((_, failures)) => failures.push(failure),
+                None => groups.push((key, vec![failure])),
+            }
+        }
+        groups
+    }
+
+    // Printed at the end of every run with failed or unfetched matches, so
+    // the cause of a failed cron run is at the bottom of its log
+    pub fn print_failure_report(&self) {
+        println!("{} of {} matches failed", self.failed, self.attempted);
+        for ((league_id, season_id), failures) in self.by_league_season() {
+            println!(
+                "{} {}: {} quarantined",
+                league_id,
+                season_id,
+                failures.len()
+            );
+            for failure in failures {
+                println!("    {}: {}", failure.match_id, failure.error);
+            }
+        }
+        if let Some(line) = self.unavailable_line() {
+            println!("{}", line);
+        }
+    }
+
+    // The same report for scrape_runs, with the errors cut short
+    fn summary(&self) -> Option<String> {
+        if !self.unfinished() {
+            return None;
+        }
+        let mut lines: Vec<String> = self
+            .by_league_season()
+            .iter()
+            .map(|((league_id, season_id), failures)| {
+                format!("{} {}: {} failed", league_id, season_id, failures.len())
+            })
+            .collect();
+        lines.extend(self.unavailable_line());
+        lines.extend(
+            self.failures
+                .iter()
+                .take(MAX_SUMMARY_ERRORS)
+                .map(|failure| format!("{}: {}", failure.match_id, failure.error)),
+        );
+        if self.failures.len() > MAX_SUMMARY_ERRORS {
+            lines.push(format!(
+                "... and {} more",
+                self.failures.len() - MAX_SUMMARY_ERRORS
+            ));
+        }
+        Some(lines.join("\n"))
+    }
+}
+
+pub struct ScrapeRun {
+    pub run_id: i32,
+    pub job: String,
+    pub command: String,
+    pub status: String,
+    pub matches_attempted: i32,
+    pub matches_succeeded: i32,
+    pub matches_failed: i32,
+    pub error_summary: Option<String>,
+    pub started_at_utc: Option<NaiveDateTime>,
+    pub finished_at_utc: Option<NaiveDateTime>,
+}
+
+fn try_lock(
+    db_client: &mut PgClient,
+    job: Job,
+    shared: bool,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let query = if shared {
+        TRY_LOCK_JOB_SHARED
+    } else {
+        TRY_LOCK_JOB
+    };
+    Ok(db_client
+        .query_one(query, &[&LOCK_NAMESPACE, &job.lock_key()])?
+        .get("locked"))
+}
+
+fn unlock(
+    db_client: &mut PgClient,
+    job: Job,
+    shared: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let query = if shared {
+        UNLOCK_JOB_SHARED
+    } else {
+        UNLOCK_JOB
+    };
+    db_client.execute(query, &[&LOCK_NAMESPACE, &job.lock_key()])?;
+    Ok(())
+}
+
+// Takes the job's locks without waiting, so a second run started by cron
+// while the first is still going exits instead of queueing behind it.
+// Session locks are released by Postgres if the process dies.
+fn lock_job(db_client: &mut PgClient, job: Job) -> Result<(), Box<dyn std::error::Error>> {
+    if !try_lock(db_client, job, false)? {
+        return Err(format!("Another {} run is in progress", job.as_str()).into());
+    }
+    for (taken, shared) in job.shared_locks().iter().enumerate() {
+        if !try_lock(db_client, *shared, true)? {
+            for held in &job.shared_locks()[..taken] {
+                unlock(db_client, *held, true)?;
+            }
+            unlock(db_client, job, false)?;
+            return Err(format!(
+                "A {} run is in progress, not starting {}",
+                shared.as_str(),
+                job.as_str()
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+fn unlock_job(db_client: &mut PgClient, job: Job) -> Result<(), Box<dyn std::error::Error>> {
+    for shared in job.shared_locks() {
+        unlock(db_client, *shared, true)?;
+    }
+    unlock(db_client, job, false)
+}
+
+fn finish_run(
+    db_client: &mut PgClient,
+    job: Job,
+    run_id: i32,
+    status: &str,
+    counts: &MatchCounts,
+    error_summary: &Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    db_client.execute(
+        FINISH_RUN,
+        &[
+            &run_id,
+            &status,
+            &counts.attempted,
+            &counts.succeeded,
+            &counts.failed,
+            error_summary,
+        ],
+    )?;
+    unlock_job(db_client, job)
+}
+
+// Runs `work` holding the job's lock and records it in scrape_runs. The run
+// row is written before the work starts, so a run that crashes is left
+// marked running until the next run of the job marks it abandoned. A run
+// with failed or unfetched matches fails even if the work itself finished.
+pub fn run_job<F>(
+    db_client: &mut PgClient,
+    job: Job,
+    command: &str,
+    config_hash: Option<String>,
+    work: F,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnOnce(&mut PgClient, &mut MatchCounts) -> Result<(), Box<dyn std::error::Error>>,
+{
+    lock_job(db_client, job)?;
+    db_client.execute(ABANDON_STALE_RUNS, &[&job.as_str()])?;
+    let run_id: i32 = db_client
+        .query_one(START_RUN, &[&job.as_str(), &command, &config_hash])?
+        .get("run_id");
+    println!("Started {} run {}", job.as_str(), run_id);
+
+    let mut counts = MatchCounts::default();
+    let mut result = work(db_client, &mut counts);
+    if counts.unfinished() {
+        counts.print_failure_report();
+        if result.is_ok() {
+            result = Err(format!(
+                "{} matches failed, {} not fetched",
+                counts.failed, counts.unavailable
+            )
+            .into());
+        }
+    }
+
+    let (status, error_summary) = match &result {
+        Ok(()) => ("succeeded", counts.summary()),
+        Err(e) => {
+            let summary = match counts.summary() {
+                Some(failures) => format!("{}\n{}", e, failures),
+                None => e.to_string(),
+            };
+            ("failed", Some(summary))
+        }
+    };
+    // A failure to record the end of the run, often the same lost
+    // connection that failed the work, is logged without hiding why the
+    // run itself failed
+    if let Err(e) = finish_run(db_client, job, run_id, status, &counts, &error_summary) {
+        println!(
+            "Could not record the end of {} run {}: {}",
+            job.as_str(),
+            run_id,
+            e
+        );
+        return result.and(Err(e));
+    }
+    println!(
+        "Finished {} run {}: {}, {} matches attempted, {} succeeded, {} failed",
+        job.as_str(),
+        run_id,
+        status,
+        counts.attempted,
+        counts.succeeded,
+        counts.failed
+    );
+    result
+}
+
+pub fn list_recent_runs(
+    db_client: &mut PgClient,
+    limit: i64,
+) -> Result<Vec<ScrapeRun>, Box<dyn std::error::Error>> {
+    let rows = db_client.query(LIST_RECENT_RUNS, &[&limit])?;
+    Ok(rows
+        .iter()
+        .map(|row| ScrapeRun {
+            run_id: row.get("run_id"),
+            job: row.get("job"),
+            command: row.get("command"),
+            status: row.get("status"),
+            matches_attempted: row.get("matches_attempted"),
+            matches_succeeded: row.get("matches_succeeded"),
+            matches_failed: row.get("matches_failed"),
+            error_summary: row.get("error_summary"),
+            started_at_utc: row.get("started_at_utc"),
+            finished_at_utc: row.get("finished_at_utc"),
+        })
+        .collect())
+}
+
+// Backs `api_scraper runs`
+pub fn print_recent_runs(
+    db_client: &mut PgClient,
+    limit: i64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let runs = list_recent_runs(db_client, limit)?;
+    println!("{} most recent runs", runs.len());
+    for run in runs {
+        let format_time = |time: Option<NaiveDateTime>| {
+            time.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| "-".to_string())
+        };
+        println!(
+            "{} | {} {} | {} | {} -> {} | matches: {} attempted, {} succeeded, {} failed",
+            run.run_id,
+            run.job,
+            run.command,
+            run.status,
+            format_time(run.started_at_utc),
+            format_time(run.finished_at_utc),
+            run.matches_attempted,
+            run.matches_succeeded,
+            run.matches_failed,
+        );
+        if let Some(summary) = run.error_summary {
+            for line in summary.lines() {
+                println!("    {}", line);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn match_info(league_id: &str, season_id: &str, match_id: &str) -> MatchInfo {
+        MatchInfo {
+            match_id: match_id.to_string(),
+            league_id: league_id.to_string(),
+            season_id: season_id.to_string(),
+            home_team_id: "943e8050".to_string(),
+            home_team_name: "Burnley".to_string(),
+            away_team_id: "b8fd03ef".to_string(),
+            away_team_name: "Manchester City".to_string(),
+        }
+    }
+
+    #[test]
+    fn unavailable_matches_are_not_match_failures() {
+        let mut counts = MatchCounts::default();
+        counts.source_unavailable();
+        counts.source_unavailable();
+        assert_eq!(counts.failed, 0);
+        assert!(counts.failures.is_empty());
+        assert!(counts.unfinished());
+        assert_eq!(
+            counts.summary().as_deref(),
+            Some("2 matches not fetched while the data source was unavailable, left pending")
+        );
+    }
+
+    #[test]
+    fn source_is_down_after_consecutive_unavailable_matches() {
+        let mut counts = MatchCounts::default();
+        for _ in 1..MAX_CONSECUTIVE_UNAVAILABLE {
+            counts.source_unavailable();
+        }
+        assert!(!counts.source_down());
+        counts.success();
+        for _ in 1..MAX_CONSECUTIVE_UNAVAILABLE {
+            counts.source_unavailable();
+        }
+        assert!(!counts.source_down());
+        counts.source_unavailable();
+        assert!(counts.source_down());
+    }
+
+    #[test]
+    fn summary_groups_failures_by_league_season() {
+        let mut counts = MatchCounts::default();
+        counts.failure(&match_info("9", "2023-2024", "a"), "missing field `home`");
+        counts.failure(&match_info("12", "2023-2024", "b"), "bad score");
+        counts.failure(&match_info("9", "2023-2024", "c"), "bad score");
+        counts.source_unavailable();
+        assert_eq!(
+            counts.summary().unwrap(),
+            "9 2023-2024: 2 failed\n\
+             12 2023-2024: 1 failed\n\
+             1 matches not fetched while the data source was unavailable, left pending\n\
+             a: missing field `home`\n\
+             b: bad score\n\
+             c: bad score"
+        );
+    }
+
+    #[test]
+    fn clean_run_has_no_summary() {
+        let mut counts = MatchCounts::default();
+        counts.attempt();
+        counts.success();
+        assert!(!counts.unfinished());
+        assert_eq!(counts.summary(), None);
+    }
+}
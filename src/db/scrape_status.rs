@@ -0,0 +1,296 @@
+use crate::db::filter::ScrapeFilter;
+use crate::db::psql::update_scrape_status::{
+    GET_MATCH_STATUS, LIST_LEAGUE_SEASON_STATUS, SET_MATCH_STATUS,
+};
+use chrono::NaiveDate;
+use postgres::{Client as PgClient, GenericClient};
+
+// pending -> fetched -> ingested is the happy path. failed matches are the
+// quarantined ones, skipped ones are never fetched, and not_yet_played ones
+// become pending once the fixture list reports them completed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchStatus {
+    Pending,
+    Fetched,
+    Ingested,
+    Failed,
+    Skipped,
+    NotYetPlayed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrapeSource {
+    Fbrapi,
+    Fbref,
+    Archive,
+}
+
+// What the fixture list says about a match. Only completed matches have
+// stats to fetch; scheduled and postponed ones wait as not_yet_played and
+// abandoned ones are skipped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FixtureStatus {
+    Scheduled,
+    Completed,
+    Postponed,
+    Abandoned,
+}
+
+impl MatchStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchStatus::Pending => "pending",
+            MatchStatus::Fetched => "fetched",
+            MatchStatus::Ingested => "ingested",
+            MatchStatus::Failed => "failed",
+            MatchStatus::Skipped => "skipped",
+            MatchStatus::NotYetPlayed => "not_yet_played",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(MatchStatus::Pending),
+            "fetched" => Some(MatchStatus::Fetched),
+            "ingested" => Some(MatchStatus::Ingested),
+            "failed" => Some(MatchStatus::Failed),
+            "skipped" => Some(MatchStatus::Skipped),
+            "not_yet_played" => Some(MatchStatus::NotYetPlayed),
+            _ => None,
+        }
+    }
+
+    // The statuses a match may move into this one from. A match left at
+    // fetched by a run that died before writing it is fetched again by the
+    // next one.
+    pub fn allowed_from(&self) -> &'static [MatchStatus] {
+        match self {
+            MatchStatus::Pending => &[
+                MatchStatus::Failed,
+                MatchStatus::Skipped,
+                MatchStatus::NotYetPlayed,
+            ],
+            MatchStatus::Fetched => &[
+                MatchStatus::Pending,
+                MatchStatus::Fetched,
+                MatchStatus::Failed,
+                MatchStatus::Ingested,
+            ],
+            MatchStatus::Ingested => &[MatchStatus::Fetched],
+            MatchStatus::Failed => &[MatchStatus::Pending, MatchStatus::Fetched],
+            MatchStatus::Skipped => &[
+                MatchStatus::Pending,
+                MatchStatus::Failed,
+                MatchStatus::NotYetPlayed,
+            ],
+            MatchStatus::NotYetPlayed => &[MatchStatus::Pending],
+        }
+    }
+}
+
+impl FixtureStatus {
+    // Notes win over the score, as abandoned matches can carry the score at
+    // the time play stopped. A past fixture without a score has not been
+    // played yet; sources that never report scores are taken at their date.
+    pub fn derive(
+        match_date: NaiveDate,
+        scored: Option<bool>,
+        notes: Option<&str>,
+        today: NaiveDate,
+    ) -> Self {
+        let notes = notes.unwrap_or_default().to_lowercase();
+        if notes.contains("abandoned") || notes.contains("cancelled") || notes.contains("awarded") {
+            FixtureStatus::Abandoned
+        } else if notes.contains("postponed") {
+            FixtureStatus::Postponed
+        } else if match_date > today {
+            FixtureStatus::Scheduled
+        } else if scored == Some(false) {
+            FixtureStatus::Postponed
+        } else {
+            FixtureStatus::Completed
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FixtureStatus::Scheduled => "scheduled",
+            FixtureStatus::Completed => "completed",
+            FixtureStatus::Postponed => "postponed",
+            FixtureStatus::Abandoned => "abandoned",
+        }
+    }
+
+    // The scrape status a match with this fixture status should sit at
+    // before its stats are fetched
+    pub fn scrape_status(&self) -> MatchStatus {
+        match self {
+            FixtureStatus::Completed => MatchStatus::Pending,
+            FixtureStatus::Scheduled | FixtureStatus::Postponed => MatchStatus::NotYetPlayed,
+            FixtureStatus::Abandoned => MatchStatus::Skipped,
+        }
+    }
+
+    pub fn is_played(&self) -> bool {
+        matches!(self, FixtureStatus::Completed | FixtureStatus::Abandoned)
+    }
+}
+
+impl ScrapeSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScrapeSource::Fbrapi => "fbrapi",
+            ScrapeSource::Fbref => "fbref",
+            ScrapeSource::Archive => "archive",
+        }
+    }
+}
+
+pub fn set_match_status(
+    db_client: &mut impl GenericClient,
+    match_id: &str,
+    status: MatchStatus,
+    source: Option<ScrapeSource>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let allowed_from: Vec<&str> = status.allowed_from().iter().map(|s| s.as_str()).collect();
+    let updated = db_client.execute(
+        SET_MATCH_STATUS,
+        &[
+            &match_id,
+            &status.as_str(),
+            &source.map(|s| s.as_str()),
+            &allowed_from,
+        ],
+    )?;
+
+    if updated == 0 {
+        let current: Option<String> = db_client
+            .query_opt(GET_MATCH_STATUS, &[&match_id])?
+            .map(|row| row.get("scrape_status"));
+        return Err(format!(
+            "Match {} cannot move from {} to {}",
+            match_id,
+            current.as_deref().unwrap_or("unknown"),
+            status.as_str()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+// A forced re-pull still goes through fetched, so matches that were never
+// played, or were abandoned, are turned away before anything is requested
+pub fn check_refetch(
+    db_client: &mut impl GenericClient,
+    match_id: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let current: String = db_client
+        .query_opt(GET_MATCH_STATUS, &[&match_id])?
+        .ok_or_else(|| format!("Match {} not found", match_id))?
+        .get("scrape_status");
+    let status = MatchStatus::parse(&current)
+        .ok_or_else(|| format!("Match {} has unknown status {}", match_id, current))?;
+
+    if !MatchStatus::Fetched.allowed_from().contains(&status) {
+        let reason = match status {
+            MatchStatus::NotYetPlayed => "it has not been played yet",
+            _ => "it was abandoned or cancelled",
+        };
+        return Err(format!(
+            "Match {} is {} and has no stats to re-pull: {}",
+            match_id, current, reason
+        )
+        .into());
+    }
+    Ok(())
+}
+
+// Backs `api_scraper status`: where every league season stands, from
+// league_season_status
+pub fn print_league_season_status(
+    db_client: &mut PgClient,
+    filter: &ScrapeFilter,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let rows = db_client.query(
+        LIST_LEAGUE_SEASON_STATUS,
+        &[&filter.league_id, &filter.season_id],
+    )?;
+    if rows.is_empty() {
+        println!("No league seasons found");
+    }
+
+    let mut totals = [0i64; 5];
+    for row in &rows {
+        let counts = [
+            row.get::<_, i64>("matches_known"),
+            row.get::<_, i64>("matches_ingested"),
+            row.get::<_, i64>("matches_pending") + row.get::<_, i64>("matches_fetched"),
+            row.get::<_, i64>("matches_failed"),
+            row.get::<_, i64>("matches_not_yet_played"),
+        ];
+        for (total, count) in totals.iter_mut().zip(counts) {
+            *total += count;
+        }
+        println!(
+            "{} {} {}: {} matches, {} ingested, {} to scrape, {} quarantined, {} not yet played",
+            row.get::<_, String>("league_id"),
+            row.get::<_, Option<String>>("league_name")
+                .unwrap_or_default(),
+            row.get::<_, String>("season_id"),
+            counts[0],
+            counts[1],
+            counts[2],
+            counts[3],
+            counts[4]
+        );
+    }
+    println!(
+        "{} league seasons: {} matches, {} ingested, {} to scrape, {} quarantined, {} not yet played",
+        rows.len(),
+        totals[0],
+        totals[1],
+        totals[2],
+        totals[3],
+        totals[4]
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_left_fetched_can_be_fetched_again() {
+        assert!(MatchStatus::Fetched
+            .allowed_from()
+            .contains(&MatchStatus::Fetched));
+    }
+
+    #[test]
+    fn unplayed_and_skipped_matches_cannot_be_fetched() {
+        for status in [MatchStatus::Skipped, MatchStatus::NotYetPlayed] {
+            assert!(!MatchStatus::Fetched.allowed_from().contains(&status));
+        }
+    }
+
+    #[test]
+    fn statuses_parse_back_from_their_names() {
+        for status in [
+            MatchStatus::Pending,
+            MatchStatus::Fetched,
+            MatchStatus::Ingested,
+            MatchStatus::Failed,
+            MatchStatus::Skipped,
+            MatchStatus::NotYetPlayed,
+        ] {
+            assert_eq!(MatchStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(MatchStatus::parse("quarantined"), None);
+    }
+
+    #[test]
+    fn only_fetched_matches_are_ingested() {
+        assert_eq!(MatchStatus::Ingested.allowed_from(), [MatchStatus::Fetched]);
+    }
+}
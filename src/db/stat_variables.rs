@@ -0,0 +1,400 @@
+use crate::db::psql::stat_variables::UPSERT_STAT_VARIABLE;
+use postgres::Client as PgClient;
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    Count,
+    Decimal,
+    Flag,
+    Text,
+}
+
+// Where a variable is read from: a field of the fbrapi player meta_data or
+// stats.summary objects, a data-stat cell of an FBref summary table, or
+// whether the player was in the starting eleven. Unreported variables are
+// never written for that source rather than guessed.
+#[derive(Debug, Clone, Copy)]
+pub enum Source {
+    MetaData(&'static str),
+    Summary(&'static str),
+    Cell(&'static str),
+    StartingEleven,
+    Unreported,
+}
+
+pub struct StatVariable {
+    pub variable: &'static str,
+    pub value_type: ValueType,
+    pub api_source: Source,
+    pub fbref_source: Source,
+    pub required: bool,
+    pub description: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub enum StatValue {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct PlayerStat {
+    pub variable: &'static str,
+    pub value: StatValue,
+}
+
+// What the repair check uses to spot partially written players: the
+// variables required since the catalogue was introduced. Matches written
+// before a variable was made required do not carry it, so variables made
+// required later are not added here.
+pub const REPAIR_VARIABLES: &[&str] = &["mins_played", "goals", "assists"];
+
+// Every per-player variable kept in player_stats. Required variables are
+// written for every player, as 0 when the source leaves them out.
+pub const STAT_VARIABLES: &[StatVariable] = &[
+    StatVariable {
+        variable: "mins_played",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("min"),
+        fbref_source: Source::Cell("minutes"),
+        required: true,
+        description: "Minutes played",
+    },
+    StatVariable {
+        variable: "goals",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("gls"),
+        fbref_source: Source::Cell("goals"),
+        required: true,
+        description: "Goals scored, excluding own goals",
+    },
+    StatVariable {
+        variable: "assists",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("ast"),
+        fbref_source: Source::Cell("assists"),
+        required: true,
+        description: "Assists",
+    },
+    StatVariable {
+        variable: "started",
+        value_type: ValueType::Flag,
+        // fbrapi has no lineup field and the order of its player list is
+        // undocumented
+        api_source: Source::Unreported,
+        fbref_source: Source::StartingEleven,
+        required: false,
+        description: "1 if the player was in the starting eleven, 0 if they came off the bench",
+    },
+    StatVariable {
+        variable: "position",
+        value_type: ValueType::Text,
+        api_source: Source::Summary("positions"),
+        fbref_source: Source::Cell("position"),
+        required: false,
+        description: "Positions played, comma separated, e.g. 'FW,LW'",
+    },
+    StatVariable {
+        variable: "shirt_number",
+        value_type: ValueType::Count,
+        api_source: Source::MetaData("player_number"),
+        fbref_source: Source::Cell("shirtnumber"),
+        required: false,
+        description: "Shirt number worn in the match",
+    },
+    StatVariable {
+        variable: "pens_made",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("pk_made"),
+        fbref_source: Source::Cell("pens_made"),
+        required: false,
+        description: "Penalty kicks scored",
+    },
+    StatVariable {
+        variable: "pens_att",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("pk_att"),
+        fbref_source: Source::Cell("pens_att"),
+        required: false,
+        description: "Penalty kicks attempted",
+    },
+    StatVariable {
+        variable: "shots",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("sh"),
+        fbref_source: Source::Cell("shots"),
+        required: false,
+        description: "Shots, excluding penalty kicks",
+    },
+    StatVariable {
+        variable: "shots_on_target",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("sot"),
+        fbref_source: Source::Cell("shots_on_target"),
+        required: false,
+        description: "Shots on target, excluding penalty kicks",
+    },
+    StatVariable {
+        variable: "yellow_cards",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("yellow_cards"),
+        fbref_source: Source::Cell("cards_yellow"),
+        required: false,
+        description: "Yellow cards",
+    },
+    StatVariable {
+        variable: "red_cards",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("red_cards"),
+        fbref_source: Source::Cell("cards_red"),
+        required: false,
+        description: "Red cards, including second yellows",
+    },
+    StatVariable {
+        variable: "touches",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("touches"),
+        fbref_source: Source::Cell("touches"),
+        required: false,
+        description: "Touches",
+    },
+    StatVariable {
+        variable: "tackles",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("tkl"),
+        fbref_source: Source::Cell("tackles"),
+        required: false,
+        description: "Tackles",
+    },
+    StatVariable {
+        variable: "interceptions",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("int"),
+        fbref_source: Source::Cell("interceptions"),
+        required: false,
+        description: "Interceptions",
+    },
+    StatVariable {
+        variable: "blocks",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("blocks"),
+        fbref_source: Source::Cell("blocks"),
+        required: false,
+        description: "Shots, passes and crosses blocked",
+    },
+    StatVariable {
+        variable: "xg",
+        value_type: ValueType::Decimal,
+        api_source: Source::Summary("xg"),
+        fbref_source: Source::Cell("xg"),
+        required: false,
+        description: "Expected goals, only available from 2017-2018",
+    },
+    StatVariable {
+        variable: "npxg",
+        value_type: ValueType::Decimal,
+        api_source: Source::Summary("non_pen_xg"),
+        fbref_source: Source::Cell("npxg"),
+        required: false,
+        description: "Non-penalty expected goals",
+    },
+    StatVariable {
+        variable: "xg_assist",
+        value_type: ValueType::Decimal,
+        api_source: Source::Summary("xg_assist"),
+        fbref_source: Source::Cell("xg_assist"),
+        required: false,
+        description: "Expected assisted goals",
+    },
+    StatVariable {
+        variable: "sca",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("sca"),
+        fbref_source: Source::Cell("sca"),
+        required: false,
+        description: "Shot-creating actions",
+    },
+    StatVariable {
+        variable: "gca",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("gca"),
+        fbref_source: Source::Cell("gca"),
+        required: false,
+        description: "Goal-creating actions",
+    },
+    StatVariable {
+        variable: "passes_completed",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("pass_cmp"),
+        fbref_source: Source::Cell("passes_completed"),
+        required: false,
+        description: "Passes completed",
+    },
+    StatVariable {
+        variable: "passes_attempted",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("pass_att"),
+        fbref_source: Source::Cell("passes"),
+        required: false,
+        description: "Passes attempted",
+    },
+    StatVariable {
+        variable: "pass_completion_pct",
+        value_type: ValueType::Decimal,
+        api_source: Source::Summary("pct_pass_cmp"),
+        fbref_source: Source::Cell("passes_pct"),
+        required: false,
+        description: "Pass completion percentage",
+    },
+    StatVariable {
+        variable: "progressive_passes",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("prog_passes"),
+        fbref_source: Source::Cell("progressive_passes"),
+        required: false,
+        description: "Progressive passes",
+    },
+    StatVariable {
+        variable: "carries",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("carries"),
+        fbref_source: Source::Cell("carries"),
+        required: false,
+        description: "Carries",
+    },
+    StatVariable {
+        variable: "progressive_carries",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("prog_carries"),
+        fbref_source: Source::Cell("progressive_carries"),
+        required: false,
+        description: "Progressive carries",
+    },
+    StatVariable {
+        variable: "take_ons_attempted",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("take_on_att"),
+        fbref_source: Source::Cell("take_ons"),
+        required: false,
+        description: "Take-ons attempted",
+    },
+    StatVariable {
+        variable: "take_ons_won",
+        value_type: ValueType::Count,
+        api_source: Source::Summary("take_on_suc"),
+        fbref_source: Source::Cell("take_ons_won"),
+        required: false,
+        description: "Take-ons won",
+    },
+];
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ValueType::Count => "count",
+            ValueType::Decimal => "decimal",
+            ValueType::Flag => "flag",
+            ValueType::Text => "text",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::MetaData(field) => write!(f, "meta_data.{}", field),
+            Source::Summary(field) => write!(f, "stats.summary.{}", field),
+            Source::Cell(stat) => write!(f, "data-stat={}", stat),
+            Source::StartingEleven => write!(f, "starting eleven"),
+            Source::Unreported => write!(f, "not reported"),
+        }
+    }
+}
+
+impl StatValue {
+    pub fn flag(set: bool) -> Self {
+        StatValue::Number(if set { 1.0 } else { 0.0 })
+    }
+}
+
+impl StatVariable {
+    // Parses a raw value as FBref and fbrapi print it, e.g. "1,234" or "87.5"
+    pub fn parse(&self, raw: &str) -> Option<StatValue> {
+        let raw = raw.trim();
+        if raw.is_empty() {
+            return None;
+        }
+        match self.value_type {
+            ValueType::Text => Some(StatValue::Text(raw.to_string())),
+            _ => raw
+                .replace(',', "")
+                .trim_end_matches('%')
+                .parse()
+                .ok()
+                .map(StatValue::Number),
+        }
+    }
+
+    pub fn from_json(&self, value: &Value) -> Option<StatValue> {
+        match value {
+            Value::Number(number) if self.value_type != ValueType::Text => {
+                number.as_f64().map(StatValue::Number)
+            }
+            Value::Number(number) => Some(StatValue::Text(number.to_string())),
+            Value::String(raw) => self.parse(raw),
+            Value::Bool(flag) => Some(StatValue::flag(*flag)),
+            _ => None,
+        }
+    }
+
+    // Required variables fall back to 0 so every player carries the full set
+    pub fn with_default(&self, value: Option<StatValue>) -> Option<PlayerStat> {
+        let value = match value {
+            Some(value) => value,
+            None if self.required => StatValue::Number(0.0),
+            None => return None,
+        };
+        Some(PlayerStat {
+            variable: self.variable,
+            value,
+        })
+    }
+}
+
+pub fn sync_stat_variables(db_client: &mut PgClient) -> Result<(), Box<dyn std::error::Error>> {
+    for stat in STAT_VARIABLES {
+        db_client.execute(
+            UPSERT_STAT_VARIABLE,
+            &[
+                &stat.variable,
+                &stat.value_type.to_string(),
+                &stat.api_source.to_string(),
+                &stat.fbref_source.to_string(),
+                &stat.required,
+                &stat.description,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A repair variable that stopped being written would make every new
+    // match look partial
+    #[test]
+    fn repair_variables_are_required() {
+        for variable in REPAIR_VARIABLES {
+            let stat = STAT_VARIABLES
+                .iter()
+                .find(|stat| stat.variable == *variable)
+                .unwrap_or_else(|| panic!("{} is not a stat variable", variable));
+            assert!(stat.required, "{} is not required", variable);
+        }
+    }
+}
@@ -1,16 +1,58 @@
-use cnxns::db::api::fixtures::get_leagues_fixtures;
-use cnxns::db::api::generate_key::generate_api_key;
-use cnxns::db::api::matches::get_match_stats;
-use cnxns::db::psql::schema::CREATE_TABLES_SQL;
-use cnxns::db::psql::update_scrape_status::CHECK_SCRAPING_COUNTS;
+use cnxns::config::{config_hash, report_config, Config, CONFIG_PATH};
+use cnxns::db::connection::{DbRole, DbSettings};
+use cnxns::db::fbref::fixtures::get_leagues_fixtures;
+use cnxns::db::fbref::matches::get_match_stats;
+use cnxns::db::ingest::{derive_player_clubs, repair_partial_matches};
+use cnxns::db::migrations::require_current_schema;
+use cnxns::db::psql::update_scrape_status::SYNC_SCRAPE_STATUS;
+use cnxns::db::runs::{run_job, Job};
+use cnxns::db::stat_variables::sync_stat_variables;
 use dotenv::dotenv;
-use postgres::{Client as PgClient, NoTls};
 use std::env;
-use std::fs;
+use std::process;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
-    let content: String = fs::read_to_string("./config.toml").expect("Failed to read config");
-    let config: toml::Value = toml::from_str(&content).expect("Failed to parse config");
-    Ok(())
+    if env::args().nth(1).as_deref() == Some("validate-config") {
+        process::exit(if report_config(CONFIG_PATH) { 0 } else { 1 });
+    }
+    let config = Config::load(CONFIG_PATH).unwrap_or_else(|e| {
+        println!("{}", e);
+        process::exit(1);
+    });
+
+    scrape_fbref(config)
+}
+
+fn scrape_fbref(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    let mut db_client = DbSettings::load(&config.db_connection, DbRole::ReadWrite)?.connect()?;
+    require_current_schema(&mut db_client)?;
+    sync_stat_variables(&mut db_client)?;
+
+    run_job(
+        &mut db_client,
+        Job::Scrape,
+        "fbref",
+        config_hash(CONFIG_PATH),
+        |db_client, counts| {
+            let repaired = repair_partial_matches(db_client)?;
+            if !repaired.is_empty() {
+                println!(
+                    "Cleared stats for {} partially ingested matches: {}",
+                    repaired.len(),
+                    repaired.join(", ")
+                );
+            }
+            db_client.batch_execute(SYNC_SCRAPE_STATUS)?;
+
+            println!("Starting fixtures...");
+            get_leagues_fixtures(db_client, &config)?;
+            println!("Fixtures complete, starting match stats...");
+            get_match_stats(db_client, &config, counts)?;
+            println!("Match stats complete");
+            derive_player_clubs(db_client)?;
+
+            Ok(())
+        },
+    )
 }
@@ -0,0 +1,32 @@
+use cnxns::config::{DbConnection, CONFIG_PATH};
+use cnxns::db::connection::{DbRole, DbSettings};
+use cnxns::db::migrations::{migrate_up, print_status};
+use dotenv::dotenv;
+use std::env;
+use std::process;
+
+const USAGE: &str = "usage: migrate [up | status]";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let db_connection = DbConnection::load(CONFIG_PATH)?;
+    let settings = DbSettings::load(&db_connection, DbRole::ReadWrite)?;
+
+    match args.as_slice() {
+        ["up"] => {
+            let applied = migrate_up(&mut settings.connect()?, &settings.schema)?;
+            if applied == 0 {
+                println!("Schema is up to date");
+            }
+            Ok(())
+        }
+        ["status"] => print_status(&mut settings.connect()?),
+        _ => {
+            println!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt;
+
+// A season as FBref writes it: "1996-1997" for split-year seasons and "2023"
+// for calendar-year ones. Seasons order by start year, so 2023 sits between
+// 2022-2023 and 2023-2024.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Season {
+    pub start_year: i32,
+    pub end_year: i32,
+}
+
+impl Season {
+    pub fn parse(season_id: &str) -> Option<Season> {
+        let years: Vec<&str> = season_id.split('-').collect();
+        let year = |raw: &str| -> Option<i32> {
+            if raw.len() == 4 && raw.chars().all(|c| c.is_ascii_digit()) {
+                raw.parse().ok()
+            } else {
+                None
+            }
+        };
+        match years.as_slice() {
+            [start, end] => {
+                let (start_year, end_year) = (year(start)?, year(end)?);
+                (end_year - start_year == 1).then_some(Season {
+                    start_year,
+                    end_year,
+                })
+            }
+            [single] => year(single).map(|start_year| Season {
+                start_year,
+                end_year: start_year,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn is_calendar_year(&self) -> bool {
+        self.start_year == self.end_year
+    }
+
+    // Matches the season_format column of competitions
+    pub fn format(&self) -> &'static str {
+        if self.is_calendar_year() {
+            "calendar_year"
+        } else {
+            "split_year"
+        }
+    }
+}
+
+impl fmt::Display for Season {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_calendar_year() {
+            write!(f, "{}", self.start_year)
+        } else {
+            write!(f, "{}-{}", self.start_year, self.end_year)
+        }
+    }
+}
@@ -67,6 +67,12 @@ function attachSearchListeners(input, dropdown) {
                         const item = document.createElement('div');
                         item.className = 'autocomplete-item';
                         item.textContent = player.player_name;
+                        if (player.detail) {
+                            const detail = document.createElement('span');
+                            detail.className = 'player-detail';
+                            detail.textContent = ` ${player.detail}`;
+                            item.appendChild(detail);
+                        }
                         item.addEventListener('click', () => {
                             input.value = player.player_name;
                             dropdown.style.display = 'none';